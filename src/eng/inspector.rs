@@ -0,0 +1,156 @@
+use super::*;
+use crate::window::{GLvec4, Instance, Window};
+use cgmath::*;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::Path;
+
+//Debug view of everything held by a Messenger. Only compiled in with the
+//`inspect` feature.
+pub struct Inspector<S: SignalType> {
+	frames: VecDeque<FrameLog<S>>,
+	frame_counter: u64,
+	pub visible: bool,
+}
+
+pub struct FrameLog<S: SignalType> {
+	pub frame: u64,
+	pub records: Vec<SignalRecord<S>>,
+}
+
+pub struct SignalRecord<S: SignalType> {
+	pub kind: S::SignalKinds,
+	pub target: Target,
+	pub sender: Option<GridId>,
+	//Seconds since the dispatch was sent
+	pub age: f32,
+	//False while waiting out its delivery delay
+	pub delivered: bool,
+}
+
+impl<S: SignalType> Inspector<S> {
+	//Frames of history kept for dumping
+	const HISTORY: usize = 600;
+	const CIRCLE_SEGMENTS: usize = 24;
+	//Radius for dispatches heard at whatever distance receivers listen at
+	const NEARBY_RADIUS: f32 = 32.;
+
+	pub const DUMP_PATH: &'static str = "signals.log";
+
+	pub fn new() -> Self {
+		Self {
+			frames: VecDeque::with_capacity(Self::HISTORY),
+			frame_counter: 0,
+			visible: false,
+		}
+	}
+
+	pub(super) fn log(&mut self, records: Vec<SignalRecord<S>>) {
+		if self.frames.len() == Self::HISTORY {
+			self.frames.pop_front();
+		}
+
+		self.frames.push_back(FrameLog {
+			frame: self.frame_counter,
+			records,
+		});
+		self.frame_counter += 1;
+	}
+
+	pub fn latest(&self) -> Option<&FrameLog<S>> {
+		self.frames.back()
+	}
+
+	pub fn frame(&self, frame: u64) -> Option<&FrameLog<S>> {
+		let oldest = self.frames.front()?.frame;
+		self.frames.get(frame.checked_sub(oldest)? as usize)
+	}
+
+	pub fn dump(&self, frame: u64, path: impl AsRef<Path>) -> io::Result<()> {
+		let log = self.frame(frame).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("frame {} is not in the history", frame),
+			)
+		})?;
+
+		let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+		log.write(&mut file)?;
+		file.flush()
+	}
+
+	pub fn render(&self, win: &mut Window) {
+		let Some(log) = self.latest() else {
+			return;
+		};
+
+		let thickness = win.external().camera.scale / 300.;
+
+		for record in &log.records {
+			let (pos, radius) = match record.target {
+				Target::Nearby(pos) => (pos, Self::NEARBY_RADIUS),
+				Target::Area(pos, radius) => (pos, radius),
+				_ => continue,
+			};
+
+			let center = Vector2::from(pos);
+			let color = Self::color(record.kind);
+			let point = |i: usize| {
+				let ang = i as f32 / Self::CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+				center + radius * vec2(ang.cos(), ang.sin())
+			};
+
+			for i in 0..Self::CIRCLE_SEGMENTS {
+				let line = win
+					.external()
+					.line_instance(point(i), point(i + 1), thickness);
+				win.queue(Instance {
+					color_tint: color,
+					..line
+				});
+			}
+		}
+	}
+
+	//Evenly spaced hues, one per signal kind
+	fn color(kind: S::SignalKinds) -> GLvec4 {
+		let hue = 6. * kind.into() as f32 / S::COUNT as f32;
+		let channel = |offset: f32| {
+			let h = (hue + offset) % 6.;
+			((h - 3.).abs() - 1.).clamp(0., 1.)
+		};
+
+		GLvec4(channel(0.), channel(4.), channel(2.), 1.)
+	}
+}
+
+impl<S: SignalType> Default for Inspector<S> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<S: SignalType> FrameLog<S> {
+	pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+		writeln!(
+			out,
+			"frame {}: {} dispatches",
+			self.frame,
+			self.records.len()
+		)?;
+
+		for record in &self.records {
+			writeln!(
+				out,
+				"{:?} {:?} sender: {:?} age: {:.3}s{}",
+				record.kind,
+				record.target,
+				record.sender,
+				record.age,
+				if record.delivered { "" } else { " (pending)" },
+			)?;
+		}
+
+		Ok(())
+	}
+}
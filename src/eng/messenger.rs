@@ -1,11 +1,15 @@
 use cgmath::*;
+use fnv::FnvHashMap;
 use std::sync::mpsc;
 use std::time::*;
 
-use super::{Grid, Griddable};
+use super::{Grid, GridId, Griddable};
+
+#[cfg(feature = "inspect")]
+use super::{Inspector, SignalRecord};
 
 pub trait SignalType {
-	type SignalKinds: Copy + Into<usize> + PartialEq;
+	type SignalKinds: Copy + Into<usize> + PartialEq + std::fmt::Debug;
 	const COUNT: usize;
 
 	fn kind(&self) -> Self::SignalKinds;
@@ -14,21 +18,58 @@ pub trait SignalType {
 pub struct Messenger<S: SignalType> {
 	now: Instant,
 	global: Buckets<S>,
-	locals: Grid<(Instant, Dispatch<S>)>,
+	locals: Grid<Delivered<S>>,
+	direct: FnvHashMap<GridId, Vec<Delivered<S>>>,
+	//Sent at the given instant, but waiting out their delivery delay
+	pending: Vec<(Instant, Dispatch<S>)>,
 	sender: mpsc::Sender<Dispatch<S>>,
 	receiver: mpsc::Receiver<Dispatch<S>>,
+	#[cfg(feature = "inspect")]
+	inspector: Inspector<S>,
 }
 
 unsafe impl<S: SignalType> Send for Messenger<S> {}
 unsafe impl<S: SignalType> Sync for Messenger<S> {}
 
-type Buckets<S> = Vec<Vec<(Instant, Dispatch<S>)>>;
+type Buckets<S> = Vec<Vec<Delivered<S>>>;
+
+struct Delivered<S: SignalType> {
+	sent: Instant,
+	//Last instant the dispatch is visible at, if it ever stops being
+	expiry: Option<Instant>,
+	dispatch: Dispatch<S>,
+}
 
 #[derive(Clone, Copy)]
 pub struct Dispatch<S: SignalType> {
-	pos: Option<(f32, f32)>,
+	target: Target,
+	sender: Option<GridId>,
 	signal: S,
-	delay: f32,
+	//Seconds between sending and first becoming visible
+	deliver_after: f32,
+	//How long the dispatch stays visible once delivered
+	ttl: Lifetime,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Lifetime {
+	//Visible to exactly one frame's update
+	Once,
+	//Visible for this many seconds
+	For(f32),
+	Forever,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Target {
+	//Heard by everyone listening for the signal kind
+	Broadcast,
+	//Heard by receivers whose listening radius covers the position
+	Nearby((f32, f32)),
+	//Like Nearby, but never heard further than the given radius
+	Area((f32, f32), f32),
+	//Heard only by the given entity
+	Entity(GridId),
 }
 
 impl<S: SignalType> Messenger<S> {
@@ -42,8 +83,12 @@ impl<S: SignalType> Messenger<S> {
 			now: Instant::now(),
 			global,
 			locals: Grid::new(256.),
+			direct: Default::default(),
+			pending: vec![],
 			sender,
 			receiver,
+			#[cfg(feature = "inspect")]
+			inspector: Inspector::new(),
 		}
 	}
 
@@ -51,38 +96,124 @@ impl<S: SignalType> Messenger<S> {
 		self.sender.clone()
 	}
 
+	//Called once per frame, after plan and before update, with the time of
+	//the frame. Anything sent before this call and due by now is visible
+	//until the next call, so plan's signals reach the same frame's update.
 	pub fn update(&mut self, now: Instant) {
 		self.now = now;
 
-		let alive = |(time, dispatch): &(Instant, Dispatch<S>)| {
-			now < (*time + Duration::from_secs_f32(dispatch.delay))
-		};
+		let alive = |delivered: &Delivered<S>| delivered.expiry.is_none_or(|expiry| now <= expiry);
 
 		for bucket in &mut self.global {
 			bucket.retain(alive)
 		}
 
+		self.direct.retain(|_, inbox| {
+			inbox.retain(alive);
+			!inbox.is_empty()
+		});
+
 		self.locals.retain(alive);
 		self.locals.maintain();
 
 		while let Ok(dispatch) = self.receiver.try_recv() {
-			if dispatch.pos.is_some() {
-				self.locals.insert((self.now, dispatch));
+			self.pending.push((now, dispatch));
+		}
+
+		let mut i = 0;
+		while i < self.pending.len() {
+			let (sent, dispatch) = &self.pending[i];
+			if *sent + Duration::from_secs_f32(dispatch.deliver_after) <= now {
+				let (sent, dispatch) = self.pending.swap_remove(i);
+				self.deliver(sent, dispatch);
 			} else {
-				let ty = dispatch.signal.kind();
-				self.global[ty.into()].push((self.now, dispatch));
+				i += 1;
+			}
+		}
+
+		#[cfg(feature = "inspect")]
+		self.inspect();
+	}
+
+	fn deliver(&mut self, sent: Instant, dispatch: Dispatch<S>) {
+		let due = sent + Duration::from_secs_f32(dispatch.deliver_after);
+
+		//Every dispatch is seen at least once, even if it was due so long
+		//ago that its time to live has already passed.
+		let expiry = match dispatch.ttl {
+			Lifetime::Once => Some(self.now),
+			Lifetime::For(ttl) => Some((due + Duration::from_secs_f32(ttl)).max(self.now)),
+			Lifetime::Forever => None,
+		};
+
+		let target = dispatch.target;
+		let ty = dispatch.signal.kind();
+
+		let delivered = Delivered {
+			sent,
+			expiry,
+			dispatch,
+		};
+
+		match target {
+			Target::Broadcast => {
+				self.global[ty.into()].push(delivered);
+			}
+			Target::Nearby(_) | Target::Area(_, _) => {
+				self.locals.insert(delivered);
+			}
+			Target::Entity(id) => {
+				self.direct.entry(id).or_default().push(delivered);
 			}
 		}
 	}
 
+	#[cfg(feature = "inspect")]
+	fn inspect(&mut self) {
+		let now = self.now;
+		let record = |delivered: bool, sent: Instant, dispatch: &Dispatch<S>| SignalRecord {
+			kind: dispatch.signal.kind(),
+			target: dispatch.target,
+			sender: dispatch.sender,
+			age: now.duration_since(sent).as_secs_f32(),
+			delivered,
+		};
+
+		let delivered = self
+			.global
+			.iter()
+			.flatten()
+			.chain(self.locals.iter())
+			.chain(self.direct.values().flatten())
+			.map(|delivered| record(true, delivered.sent, &delivered.dispatch));
+
+		let pending = self
+			.pending
+			.iter()
+			.map(|(sent, dispatch)| record(false, *sent, dispatch));
+
+		let records = delivered.chain(pending).collect();
+		self.inspector.log(records);
+	}
+
+	#[cfg(feature = "inspect")]
+	pub fn inspector(&self) -> &Inspector<S> {
+		&self.inspector
+	}
+
+	#[cfg(feature = "inspect")]
+	pub fn inspector_mut(&mut self) -> &mut Inspector<S> {
+		&mut self.inspector
+	}
+
 	pub fn global_receive<'a>(
 		&'a self,
 		types: &'a [S::SignalKinds],
-	) -> impl Iterator<Item = &'a S> + 'a {
+	) -> impl Iterator<Item = &'a Dispatch<S>> + 'a {
 		types
 			.iter()
 			.flat_map(|&ty| self.global[ty.into()].iter())
-			.map(|(_, dispatch)| &dispatch.signal)
+			.map(|delivered| &delivered.dispatch)
 	}
 
 	pub fn local_receive<'a>(
@@ -90,34 +221,286 @@ impl<S: SignalType> Messenger<S> {
 		pos: Vector2<f32>,
 		radius: f32,
 		types: &'a [S::SignalKinds],
-	) -> impl Iterator<Item = ((f32, f32), &'a S)> + 'a {
+	) -> impl Iterator<Item = &'a Dispatch<S>> + 'a {
 		self.locals
-			.query_at(pos, radius)
-			.map(|(_id, (_time, dispatch))| (dispatch.pos.unwrap(), &dispatch.signal))
-			.filter(|(_, signal)| types.contains(&signal.kind()))
+			.query_with_dist(pos, radius)
+			.filter(|(dist, (_id, delivered))| match delivered.dispatch.target {
+				Target::Area(_, reach) => *dist <= reach,
+				_ => true,
+			})
+			.map(|(_, (_id, delivered))| &delivered.dispatch)
+			.filter(|dispatch| types.contains(&dispatch.signal.kind()))
+	}
+
+	pub fn direct_receive<'a>(
+		&'a self,
+		id: GridId,
+		types: &'a [S::SignalKinds],
+	) -> impl Iterator<Item = &'a Dispatch<S>> + 'a {
+		self.direct
+			.get(&id)
+			.into_iter()
+			.flatten()
+			.map(|delivered| &delivered.dispatch)
+			.filter(|dispatch| types.contains(&dispatch.signal.kind()))
 	}
 }
 
 impl<S: SignalType> Dispatch<S> {
-	pub fn global(signal: S, delay: f32) -> Self {
+	pub fn global(signal: S) -> Self {
+		Self::new(Target::Broadcast, signal)
+	}
+
+	pub fn local(pos: (f32, f32), signal: S) -> Self {
+		Self::new(Target::Nearby(pos), signal)
+	}
+
+	pub fn area(pos: (f32, f32), radius: f32, signal: S) -> Self {
+		Self::new(Target::Area(pos, radius), signal)
+	}
+
+	pub fn direct(to: GridId, signal: S) -> Self {
+		Self::new(Target::Entity(to), signal)
+	}
+
+	fn new(target: Target, signal: S) -> Self {
 		Self {
-			pos: None,
+			target,
+			sender: None,
 			signal,
-			delay,
+			deliver_after: 0.,
+			ttl: Lifetime::Once,
 		}
 	}
 
-	pub fn local(pos: (f32, f32), signal: S, delay: f32) -> Self {
+	pub fn after(self, deliver_after: f32) -> Self {
 		Self {
-			pos: Some(pos),
-			signal,
-			delay,
+			deliver_after,
+			..self
+		}
+	}
+
+	//Keeps the dispatch visible for ttl seconds instead of a single frame
+	pub fn lasting(self, ttl: f32) -> Self {
+		Self {
+			ttl: Lifetime::For(ttl),
+			..self
+		}
+	}
+
+	//Keeps the dispatch visible for good
+	pub fn persistent(self) -> Self {
+		Self {
+			ttl: Lifetime::Forever,
+			..self
+		}
+	}
+
+	pub fn sent_by(self, sender: GridId) -> Self {
+		Self {
+			sender: Some(sender),
+			..self
+		}
+	}
+
+	pub fn signal(&self) -> &S {
+		&self.signal
+	}
+
+	pub fn sender(&self) -> Option<GridId> {
+		self.sender
+	}
+
+	pub fn target(&self) -> Target {
+		self.target
+	}
+
+	//None for dispatches that are not tied to a place
+	pub fn pos(&self) -> Option<(f32, f32)> {
+		match self.target {
+			Target::Nearby(pos) | Target::Area(pos, _) => Some(pos),
+			_ => None,
 		}
 	}
 }
 
-impl<S: SignalType> Griddable for (Instant, Dispatch<S>) {
+impl<S: SignalType> Griddable for Delivered<S> {
 	fn pos(&self) -> (f32, f32) {
-		self.1.pos.expect("Global dispatch.")
+		self.dispatch.pos().expect("Dispatch without a position.")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::FreeList;
+	use super::*;
+
+	#[derive(Clone, Copy)]
+	struct Ping;
+
+	impl SignalType for Ping {
+		type SignalKinds = usize;
+		const COUNT: usize = 1;
+
+		fn kind(&self) -> usize {
+			0
+		}
+	}
+
+	//Long enough that delays land exactly on frames
+	const FRAME: f32 = 0.25;
+
+	struct Frames {
+		messenger: Messenger<Ping>,
+		start: Instant,
+		frame: u32,
+	}
+
+	impl Frames {
+		fn new() -> Self {
+			Self {
+				messenger: Messenger::new(),
+				start: Instant::now(),
+				frame: 0,
+			}
+		}
+
+		fn send(&self, dispatch: Dispatch<Ping>) {
+			self.messenger.sender().send(dispatch).unwrap();
+		}
+
+		//Ends plan and starts update for the next frame
+		fn step(&mut self) {
+			let now = self.start + Duration::from_secs_f32(self.frame as f32 * FRAME);
+			self.messenger.update(now);
+			self.frame += 1;
+		}
+
+		fn visible(&self) -> usize {
+			self.messenger.global_receive(&[0]).count()
+		}
+
+		//Which of the next n frames' updates see a dispatch
+		fn seen_over(&mut self, n: usize) -> Vec<bool> {
+			self.seen_by(n, |messenger| messenger.global_receive(&[0]).count() > 0)
+		}
+
+		fn seen_by(&mut self, n: usize, seen: impl Fn(&Messenger<Ping>) -> bool) -> Vec<bool> {
+			(0..n)
+				.map(|_| {
+					self.step();
+					seen(&self.messenger)
+				})
+				.collect()
+		}
+	}
+
+	fn entity_ids() -> (GridId, GridId) {
+		let mut ids = FreeList::new();
+		(ids.insert(()), ids.insert(()))
+	}
+
+	#[test]
+	fn delivered_once_for_one_frame() {
+		let mut frames = Frames::new();
+		frames.send(Dispatch::global(Ping));
+		assert_eq!(frames.seen_over(3), [true, false, false]);
+	}
+
+	#[test]
+	fn sent_in_plan_seen_by_same_update() {
+		let mut frames = Frames::new();
+		frames.step();
+
+		//Sent during update, so not seen until the next frame
+		frames.send(Dispatch::global(Ping));
+		assert_eq!(frames.visible(), 0);
+
+		//Sent during the next frame's plan, so seen by its update
+		frames.send(Dispatch::global(Ping));
+		frames.step();
+		assert_eq!(frames.visible(), 2);
+
+		frames.step();
+		assert_eq!(frames.visible(), 0);
+	}
+
+	#[test]
+	fn delayed_delivery() {
+		let mut frames = Frames::new();
+		frames.send(Dispatch::global(Ping).after(2. * FRAME));
+		assert_eq!(frames.seen_over(5), [false, false, true, false, false]);
+	}
+
+	#[test]
+	fn lasting_dispatches_expire() {
+		let mut frames = Frames::new();
+		frames.send(Dispatch::global(Ping).lasting(2. * FRAME));
+		assert_eq!(frames.seen_over(5), [true, true, true, false, false]);
+
+		//Delay and time to live add up
+		frames.send(Dispatch::global(Ping).after(FRAME).lasting(FRAME));
+		assert_eq!(frames.seen_over(4), [false, true, true, false]);
+	}
+
+	#[test]
+	fn persistent_dispatches_never_expire() {
+		let mut frames = Frames::new();
+		frames.send(Dispatch::global(Ping).persistent());
+		assert!(frames.seen_over(1000).into_iter().all(|seen| seen));
+	}
+
+	#[test]
+	fn entity_dispatches_reach_only_their_target() {
+		let (a, b) = entity_ids();
+		let mut frames = Frames::new();
+		frames.send(Dispatch::direct(a, Ping));
+		frames.step();
+
+		let messenger = &frames.messenger;
+		assert_eq!(messenger.direct_receive(a, &[0]).count(), 1);
+		assert_eq!(messenger.direct_receive(b, &[0]).count(), 0);
+		assert_eq!(messenger.global_receive(&[0]).count(), 0);
+		assert_eq!(
+			messenger
+				.local_receive(vec2(0., 0.), f32::INFINITY, &[0])
+				.count(),
+			0
+		);
+	}
+
+	#[test]
+	fn area_dispatches_not_heard_beyond_reach() {
+		let mut frames = Frames::new();
+		frames.send(Dispatch::area((0., 0.), 10., Ping));
+		frames.send(Dispatch::local((1000., 0.), Ping));
+		frames.step();
+
+		let heard = |pos, radius| frames.messenger.local_receive(pos, radius, &[0]).count();
+		assert_eq!(heard(vec2(5., 0.), 10.), 1);
+		//Listening far enough to cover the area's centre isn't enough
+		assert_eq!(heard(vec2(50., 0.), 100.), 0);
+		//Nearby dispatches have no reach of their own
+		assert_eq!(heard(vec2(1050., 0.), 100.), 1);
+	}
+
+	#[test]
+	fn direct_and_local_dispatches_expire() {
+		let (a, _) = entity_ids();
+		let mut frames = Frames::new();
+
+		frames.send(Dispatch::direct(a, Ping).lasting(2. * FRAME));
+		let seen = frames.seen_by(5, |messenger| messenger.direct_receive(a, &[0]).count() > 0);
+		assert_eq!(seen, [true, true, true, false, false]);
+
+		frames.send(
+			Dispatch::area((0., 0.), 10., Ping)
+				.after(FRAME)
+				.lasting(FRAME),
+		);
+		let seen = frames.seen_by(4, |messenger| {
+			messenger.local_receive(vec2(0., 0.), 1., &[0]).count() > 0
+		});
+		assert_eq!(seen, [false, true, true, false]);
 	}
 }
@@ -1,12 +1,18 @@
 #![allow(dead_code)]
 
+#[cfg(feature = "inspect")]
+pub mod inspector;
 pub mod messenger;
 pub mod play;
+pub mod replay;
 pub mod state;
 pub mod utils;
 
+#[cfg(feature = "inspect")]
+pub use inspector::*;
 pub use messenger::*;
 pub use play::*;
+pub use replay::*;
 pub use std::sync::mpsc::Sender;
 pub use utils::*;
 
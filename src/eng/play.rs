@@ -2,15 +2,31 @@ use winit::event::{Event, WindowEvent};
 use winit::event_loop::EventLoop;
 
 use super::*;
-use crate::window::{Camera, TextureType};
+use crate::window::{Camera, InputEvent, TextureType};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 pub trait Root: Sized + 'static {
 	const TITLE: &'static str = "Game Window";
 
+	//Simulation steps per second. plan & update always see a delta of
+	//exactly 1 / TICK_RATE, however fast frames are rendered.
+	const TICK_RATE: f32 = 60.;
+	//Most steps run in a single frame before the simulation gives up on
+	//catching up to real time.
+	const MAX_CATCH_UP: u32 = 8;
+
 	type Texture: TextureType;
 	type Signal: SignalType;
+	//How the world is set up, as chosen on the command line. Stored in
+	//replays so they start from the same world.
+	type Options: Clone + Default + Serialize + DeserializeOwned;
+
+	fn parse_args() -> Self::Options {
+		Default::default()
+	}
 
-	fn init(external: &External) -> Self;
+	fn init(external: &External, options: &Self::Options) -> Self;
 	fn camera(&self, inputs: &External) -> Camera;
 
 	fn plan(&self, _external: &External, _messenger: &Sender<Dispatch<Self::Signal>>);
@@ -38,8 +54,39 @@ pub fn play<World: Root>() -> ! {
 		None
 	};
 
+	//Pass `--replay <file>` to watch a session saved with `--record <file>`
+	let replay = arg("--replay")
+		.map(|path| Replay::<World::Options>::load(path).expect("Unable to load replay."));
+	let record = arg("--record");
+
+	let options = match &replay {
+		Some(replay) => replay.options.clone(),
+		None => World::parse_args(),
+	};
+
+	//Pass `--seed <n>` to reproduce a previous run
+	let seed = match &replay {
+		Some(replay) => replay.seed,
+		None => arg("--seed")
+			.map(|seed| seed.parse().expect("Seed must be an unsigned integer."))
+			.unwrap_or_else(rand::random),
+	};
+	println!("seed: {}", seed);
+
 	let event_loop = EventLoop::new();
-	let mut game = state::GameState::<World>::new(&event_loop);
+	let mut game = state::GameState::<World>::new(&event_loop, seed, options);
+
+	if record.is_some() {
+		game.record();
+	}
+
+	//Mouse positions are relative to the window, so the world keeps seeing
+	//the recorded size whatever the real window's is
+	let mut playback = replay.map(|replay| {
+		game.external_mut().win_size = replay.win_size;
+		game.external_mut().recording = true;
+		replay.frames.into_iter()
+	});
 
 	let mut prev = std::time::Instant::now();
 	let mut frame_counter = 0;
@@ -47,50 +94,43 @@ pub fn play<World: Root>() -> ! {
 	event_loop.run(move |event, _, flow| {
 		flow.set_poll();
 		match event {
-			Event::WindowEvent { event, window_id } if window_id == game.win.id() => match event {
-				WindowEvent::CloseRequested => {
-					flow.set_exit();
-				}
-
-				WindowEvent::Resized(dims) if dims.height != 0 && dims.width != 0 => {
-					game.win.resize(dims);
-				}
-
-				WindowEvent::KeyboardInput { input, .. } => {
-					game.win.external_mut().capture_key(input);
+			Event::WindowEvent { event, window_id } if game.win.id() == Some(window_id) => {
+				match event {
+					WindowEvent::CloseRequested => {
+						if let Some((path, replay)) = record.as_ref().zip(game.stop_recording()) {
+							replay.save(path).expect("Unable to save replay.");
+						}
+						flow.set_exit();
+					}
+
+					WindowEvent::Destroyed => {
+						tracing_guard.take();
+						flow.set_exit()
+					}
+
+					//The surface must always follow the real window, even
+					//while live input is ignored during playback
+					WindowEvent::Resized(dims) if dims.height != 0 && dims.width != 0 => {
+						game.win.resize_surface(dims);
+						if playback.is_none() {
+							game.input(InputEvent::Resized(dims.width, dims.height));
+						}
+					}
+
+					event if playback.is_none() => {
+						if let Some(input) = InputEvent::from_winit(&event) {
+							game.input(input);
+						}
+					}
+
+					_ => {}
 				}
-
-				WindowEvent::MouseWheel { delta, .. } => {
-					use winit::dpi::PhysicalPosition;
-					use winit::event::MouseScrollDelta::*;
-					game.win.external_mut().scroll = match delta {
-						LineDelta(_hor, ver) => ver,
-						PixelDelta(PhysicalPosition { y, .. }) => y as f32,
-					};
-				}
-
-				WindowEvent::CursorMoved { position, .. } => {
-					let size = game.win.external().win_size;
-					game.win.external_mut().capture_mouse(&position, size);
-				}
-
-				WindowEvent::MouseInput { button, state, .. } => game
-					.win
-					.external_mut()
-					.mouse_button(&button, state == winit::event::ElementState::Pressed),
-
-				WindowEvent::Destroyed => {
-					tracing_guard.take();
-					flow.set_exit()
-				}
-
-				_ => {}
-			},
+			}
 
 			Event::MainEventsCleared => {
 				const FPS_FREQ: f64 = 5.;
 				frame_counter += 1;
-				let now = game.win.external().now;
+				let now = std::time::Instant::now();
 				let time = now.duration_since(prev).as_secs_f64();
 				if time > FPS_FREQ {
 					println!("fps: {}", (frame_counter as f64 / FPS_FREQ) as i32);
@@ -100,10 +140,23 @@ pub fn play<World: Root>() -> ! {
 					game.cleanup();
 				}
 
-				game.frame();
+				match playback.as_mut().map(Iterator::next) {
+					Some(Some(frame)) => game.play_frame(&frame),
+					Some(None) => {
+						println!("replay finished");
+						playback = None;
+						game.frame();
+					}
+					None => game.frame(),
+				}
 			}
 
 			_ => {}
 		}
 	})
 }
+
+//Value following `name` on the command line, if any
+pub fn arg(name: &str) -> Option<String> {
+	std::env::args().skip_while(|arg| arg != name).nth(1)
+}
@@ -0,0 +1,73 @@
+//Per-frame log of every input a GameState received, along with the time
+//each frame took. Together with the seed and the options the world was
+//started with, this is enough to reproduce a session exactly when fed
+//back through GameState::play_frame.
+
+use crate::window::InputEvent;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize)]
+pub struct Replay<Options> {
+	version: u32,
+	pub seed: u64,
+	pub win_size: (u32, u32),
+	pub options: Options,
+	pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ReplayFrame {
+	pub elapsed: Duration,
+	pub events: Vec<InputEvent>,
+}
+
+//Read on its own first, so replays of other versions are rejected before
+//the rest is parsed
+#[derive(Deserialize)]
+struct Header {
+	version: u32,
+}
+
+impl<Options: Serialize + DeserializeOwned> Replay<Options> {
+	//Bump whenever the format changes. Replays from other versions are rejected.
+	pub const VERSION: u32 = 2;
+
+	pub fn new(seed: u64, win_size: (u32, u32), options: Options) -> Self {
+		Self {
+			version: Self::VERSION,
+			seed,
+			win_size,
+			options,
+			frames: vec![],
+		}
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		let text = std::fs::read_to_string(path)?;
+		let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+
+		let header: Header = ron::from_str(&text).map_err(invalid)?;
+		if header.version != Self::VERSION {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"replay version {} is unsupported (expected {})",
+					header.version,
+					Self::VERSION
+				),
+			));
+		}
+
+		ron::from_str(&text).map_err(invalid)
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let text =
+			ron::to_string(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		std::fs::write(path, text)
+	}
+}
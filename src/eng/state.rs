@@ -1,6 +1,7 @@
 use super::*;
 
-use crate::window::Window;
+use crate::window::{Camera, InputEvent, Recording, Window};
+use std::time::{Duration, Instant};
 use winit::event_loop::EventLoop;
 
 #[cfg(feature = "profile")]
@@ -10,41 +11,224 @@ pub struct GameState<World: Root> {
 	pub(super) win: Window,
 	messenger: Messenger<World::Signal>,
 	world: World,
+	last_frame: Instant,
+	accumulator: f32,
+	//Camera as of the step before last, to interpolate from
+	last_camera: Camera,
+	//What the world was started with, for recordings to start from
+	options: World::Options,
+	recorder: Option<Replay<World::Options>>,
+	pending: Vec<InputEvent>,
+	//Whether any frame has run yet
+	started: bool,
 }
 
 impl<World: Root> GameState<World> {
-	pub fn new(event_loop: &EventLoop<()>) -> Self {
-		let api = Window::new::<World::Texture>(World::TITLE, event_loop);
+	pub fn new(event_loop: &EventLoop<()>, seed: u64, options: World::Options) -> Self {
+		let win = Window::new::<World::Texture>(World::TITLE, event_loop, seed);
+		Self::with_window(win, options)
+	}
+
+	//Runs without a display. Frames must be driven manually with
+	//GameState::advance, and rendering is only recorded.
+	pub fn headless(win_size: (u32, u32), seed: u64, options: World::Options) -> Self {
+		Self::with_window(Window::headless::<World::Texture>(win_size, seed), options)
+	}
+
+	//Headless game in the state the replay was recorded from. Its frames
+	//still need to be played with GameState::play_frame or play_back.
+	pub fn from_replay(replay: &Replay<World::Options>) -> Self {
+		let mut game = Self::headless(replay.win_size, replay.seed, replay.options.clone());
+		game.external_mut().recording = true;
+		game
+	}
+
+	fn with_window(api: Window, options: World::Options) -> Self {
 		Self {
-			world: World::init(api.external()),
+			world: World::init(api.external(), &options),
+			options,
 			messenger: Messenger::new(),
+			last_frame: api.external().now,
+			accumulator: 0.,
+			last_camera: api.external().camera,
+			recorder: None,
+			pending: vec![],
+			started: false,
 			win: api,
 		}
 	}
 
 	#[cfg_attr(feature = "profile", instrument(skip_all, name = "Frame"))]
 	pub fn frame(&mut self) {
-		self.step();
+		self.frame_at(Instant::now());
+	}
+
+	//Render a frame as if `delta` seconds have passed since the last one,
+	//regardless of wall-clock time.
+	pub fn advance(&mut self, delta: f32) {
+		self.advance_by(Duration::from_secs_f32(delta));
+	}
+
+	pub fn advance_by(&mut self, elapsed: Duration) {
+		self.frame_at(self.last_frame + elapsed);
+	}
+
+	pub fn input(&mut self, event: InputEvent) {
+		self.win.handle(event);
+		if self.recorder.is_some() {
+			self.pending.push(event);
+		}
+	}
+
+	//Start logging inputs & frame times. Replays start from a newly
+	//initialised world, so this must be called before the first frame.
+	pub fn record(&mut self) {
+		assert!(!self.started, "Recording must start before the first frame");
+
+		let seed = self.win.external().rng.seed();
+		let win_size = self.win.external().win_size;
+		self.recorder = Some(Replay::new(seed, win_size, self.options.clone()));
+		self.pending.clear();
+		self.external_mut().recording = true;
+	}
+
+	pub fn stop_recording(&mut self) -> Option<Replay<World::Options>> {
+		self.external_mut().recording = false;
+		self.recorder.take()
+	}
+
+	pub fn play_frame(&mut self, frame: &ReplayFrame) {
+		for &event in &frame.events {
+			self.input(event);
+		}
+		self.advance_by(frame.elapsed);
+	}
+
+	pub fn play_back(&mut self, replay: &Replay<World::Options>) {
+		for frame in &replay.frames {
+			self.play_frame(frame);
+		}
+	}
+
+	pub fn tick_duration() -> f32 {
+		1. / World::TICK_RATE
+	}
+
+	pub fn world(&self) -> &World {
+		&self.world
+	}
+
+	pub fn world_mut(&mut self) -> &mut World {
+		&mut self.world
+	}
+
+	pub fn external(&self) -> &External {
+		self.win.external()
+	}
+
+	//Inputs written here are seen by the next frame
+	pub fn external_mut(&mut self) -> &mut External {
+		self.win.external_mut()
+	}
+
+	//What was drawn on the last frame, if headless
+	pub fn recording(&self) -> Option<&Recording> {
+		self.win.recording()
+	}
+
+	pub fn title(&self) -> &str {
+		self.win.title()
+	}
+
+	fn frame_at(&mut self, now: Instant) {
+		let tick = Self::tick_duration();
+		let max_lag = World::MAX_CATCH_UP as f32 * tick;
+
+		let elapsed = now.duration_since(self.last_frame);
+		self.last_frame = now;
+		self.started = true;
+
+		if let Some(recorder) = &mut self.recorder {
+			recorder.frames.push(ReplayFrame {
+				elapsed,
+				events: std::mem::take(&mut self.pending),
+			});
+		}
+
+		//Time beyond max_lag is dropped, so the simulation slows down
+		//rather than spiralling when steps take longer than a tick.
+		self.accumulator = (self.accumulator + elapsed.as_secs_f32()).min(max_lag);
+		while self.accumulator >= tick {
+			self.step();
+			self.accumulator -= tick;
+		}
+
+		let alpha = self.accumulator / tick;
+		self.win.external_mut().alpha = alpha;
+
+		//Frames are drawn between the last two steps, so the camera and
+		//anything animated over time move smoothly too. Rendering can't
+		//change the world, so this doesn't affect the simulation.
+		let external = self.win.external_mut();
+		let (now, camera) = (external.now, external.camera);
+		external.now = now - Duration::from_secs_f32((1. - alpha) * tick);
+		external.camera = self.last_camera.lerp(camera, alpha);
+
 		self.draw();
 		self.win.submit();
+
+		let external = self.win.external_mut();
+		external.now = now;
+		external.camera = camera;
 	}
 
 	#[cfg_attr(feature = "profile", instrument(skip_all, name = "Game Step"))]
 	fn step(&mut self) {
+		let now = self.win.external().now + Duration::from_secs_f32(Self::tick_duration());
+
 		self.world
 			.plan(self.win.external(), &self.messenger.sender());
+		self.messenger.update(self.win.external().now);
 		self.world.update(self.win.external(), &self.messenger);
+
+		#[cfg(feature = "inspect")]
+		self.inspect();
+		self.last_camera = self.win.external().camera;
 		self.win.external_mut().camera = self.world.camera(self.win.external());
 
-		let now = std::time::Instant::now();
 		self.win.external_mut().update(now);
-		self.messenger.update(now);
 	}
 
 	#[cfg_attr(feature = "profile", instrument(skip_all, name = "Drawing"))]
 	fn draw(&mut self) {
 		self.win.clear();
 		self.world.render(&mut self.win);
+
+		#[cfg(feature = "inspect")]
+		if self.messenger.inspector().visible {
+			self.messenger.inspector().render(&mut self.win);
+		}
+	}
+
+	//F3 toggles the signal overlay, F4 dumps this frame's signals
+	#[cfg(feature = "inspect")]
+	fn inspect(&mut self) {
+		use winit::event::VirtualKeyCode::*;
+
+		let inspector = self.messenger.inspector_mut();
+
+		if self.win.external().key(F3).pressed() {
+			inspector.visible = !inspector.visible;
+		}
+
+		if self.win.external().key(F4).pressed() {
+			if let Some(frame) = inspector.latest().map(|log| log.frame) {
+				match inspector.dump(frame, Inspector::<World::Signal>::DUMP_PATH) {
+					Ok(()) => println!("dumped frame {} signals", frame),
+					Err(err) => println!("unable to dump signals: {}", err),
+				}
+			}
+		}
 	}
 
 	#[cfg_attr(feature = "profile", instrument(skip_all, name = "Cleanup"))]
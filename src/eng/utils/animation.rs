@@ -29,9 +29,17 @@ pub struct Animation<Texture: TextureType> {
 }
 
 impl<Texture: TextureType> Animation<Texture> {
-	pub fn new(texture: Texture, duration: f32, curve: fn(f32) -> f32, repeat: f32) -> Self {
+	//`start` should come from External::now, not the wall clock, so that
+	//animations follow simulation time.
+	pub fn new(
+		start: Instant,
+		texture: Texture,
+		duration: f32,
+		curve: fn(f32) -> f32,
+		repeat: f32,
+	) -> Self {
 		Self {
-			start: Instant::now(),
+			start,
 			texture,
 			duration,
 			curve,
@@ -62,7 +70,7 @@ impl<Texture: TextureType> Animation<Texture> {
 		now.duration_since(self.start).as_secs_f32()
 	}
 
-	pub fn restart(&mut self) {
-		self.start = Instant::now()
+	pub fn restart(&mut self, now: Instant) {
+		self.start = now
 	}
 }
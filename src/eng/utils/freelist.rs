@@ -1,13 +1,17 @@
+use fnv::FnvHashMap;
 use rayon::iter::*;
 use std::ops::{Index, IndexMut};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+//Generational index: the id is unique to each insertion, so an id whose
+//slot has since been freed or reused never refers to the new occupant.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct FreeListEntryId(usize, usize);
 
 pub struct FreeList<T> {
 	inner: Vec<Elem<T>>,
 	free: Option<usize>,
 	id_counter: usize,
+	len: usize,
 }
 
 impl<T> FreeList<T> {
@@ -16,43 +20,50 @@ impl<T> FreeList<T> {
 			inner: vec![],
 			free: None,
 			id_counter: 0,
+			len: 0,
 		}
 	}
 
+	//None if the entry has been removed
 	pub fn get(&self, index: FreeListEntryId) -> Option<&T> {
 		let FreeListEntryId(id, index) = index;
 
-		match &self.inner[index] {
-			Elem::Entry(eid, item) if id == *eid => Some(item),
-			Elem::Entry(_, _) => None,
-			_ => panic!("Attempted to access empty freelist slot."),
+		match self.inner.get(index) {
+			Some(Elem::Entry(eid, item)) if id == *eid => Some(item),
+			_ => None,
 		}
 	}
 
 	pub fn get_mut(&mut self, index: FreeListEntryId) -> Option<&mut T> {
 		let FreeListEntryId(id, index) = index;
 
-		match &mut self.inner[index] {
-			Elem::Entry(eid, item) if id == *eid => Some(item),
-			Elem::Entry(_, _) => None,
-			_ => panic!("Attempted to access empty freelist slot."),
+		match self.inner.get_mut(index) {
+			Some(Elem::Entry(eid, item)) if id == *eid => Some(item),
+			_ => None,
 		}
 	}
 
+	pub fn contains(&self, index: FreeListEntryId) -> bool {
+		self.get(index).is_some()
+	}
+
+	//Number of slots, occupied or not
 	pub fn slot_count(&self) -> usize {
-		self.inner.capacity()
+		self.inner.len()
 	}
 
-	pub fn count(&self) -> usize {
-		self.inner
-			.iter()
-			.filter(|e| matches!(e, Elem::Entry(_, _)))
-			.count()
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
 	}
 
 	pub fn insert(&mut self, item: T) -> FreeListEntryId {
 		let id = self.id_counter;
 		self.id_counter += 1;
+		self.len += 1;
 
 		if let Some(first_free) = self.free {
 			self.free = match self.inner[first_free] {
@@ -72,24 +83,56 @@ impl<T> FreeList<T> {
 	pub fn remove(&mut self, index: FreeListEntryId) -> Option<T> {
 		let FreeListEntryId(id, index) = index;
 
-		match &mut self.inner[index] {
-			Elem::Entry(eid, _) if id == *eid => {
-				let obj = std::mem::replace(&mut self.inner[index], Elem::LastFree);
+		match self.inner.get(index) {
+			Some(Elem::Entry(eid, _)) if id == *eid => Some(self.free_slot(index)),
+			_ => None,
+		}
+	}
+
+	pub fn retain<P: FnMut(&T) -> bool>(&mut self, mut predicate: P) {
+		for i in 0..self.inner.len() {
+			if matches!(&self.inner[i], Elem::Entry(_, item) if !predicate(item)) {
+				self.free_slot(i);
+			}
+		}
+	}
 
-				if let Some(free) = self.free {
-					self.inner[index] = Elem::NextFree(free);
-				};
+	//Removes every entry, leaving no slots behind
+	pub fn drain(&mut self) -> impl Iterator<Item = T> {
+		self.free = None;
+		self.len = 0;
 
-				self.free = Some(index);
+		std::mem::take(&mut self.inner)
+			.into_iter()
+			.filter_map(|elem| match elem {
+				Elem::Entry(_, item) => Some(item),
+				_ => None,
+			})
+	}
 
-				let Elem::Entry(_, obj) = obj else {
-					unreachable!()
-				};
+	//Moves all entries to the front and drops the free slots. Every entry
+	//gets a new id, so the map from old to new ids is returned.
+	pub fn compact(&mut self) -> FnvHashMap<FreeListEntryId, FreeListEntryId> {
+		let mut remap = FnvHashMap::default();
+		remap.reserve(self.len);
 
-				Some(obj)
-			}
-			_ => None,
+		self.free = None;
+
+		let slots = std::mem::take(&mut self.inner);
+		for (old_slot, elem) in slots.into_iter().enumerate() {
+			let Elem::Entry(id, item) = elem else {
+				continue;
+			};
+
+			let new_id = self.id_counter;
+			self.id_counter += 1;
+
+			let slot = self.inner.len();
+			remap.insert(FreeListEntryId(id, old_slot), FreeListEntryId(new_id, slot));
+			self.inner.push(Elem::Entry(new_id, item));
 		}
+
+		remap
 	}
 
 	pub fn sort_frees(&mut self) {
@@ -130,6 +173,43 @@ impl<T> FreeList<T> {
 			_ => None,
 		})
 	}
+
+	pub fn iter_with_ids(&self) -> impl Iterator<Item = (FreeListEntryId, &T)> {
+		self.inner
+			.iter()
+			.enumerate()
+			.filter_map(|(slot, elem)| match elem {
+				Elem::Entry(id, item) => Some((FreeListEntryId(*id, slot), item)),
+				_ => None,
+			})
+	}
+
+	pub fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (FreeListEntryId, &mut T)> {
+		self.inner
+			.iter_mut()
+			.enumerate()
+			.filter_map(|(slot, elem)| match elem {
+				Elem::Entry(id, item) => Some((FreeListEntryId(*id, slot), item)),
+				_ => None,
+			})
+	}
+
+	//Slot must hold an entry
+	fn free_slot(&mut self, index: usize) -> T {
+		let next = match self.free {
+			Some(free) => Elem::NextFree(free),
+			None => Elem::LastFree,
+		};
+
+		let Elem::Entry(_, obj) = std::mem::replace(&mut self.inner[index], next) else {
+			unreachable!()
+		};
+
+		self.free = Some(index);
+		self.len -= 1;
+
+		obj
+	}
 }
 
 impl<T: Send + Sync> FreeList<T> {
@@ -146,30 +226,34 @@ impl<T: Send + Sync> FreeList<T> {
 			_ => None,
 		})
 	}
+
+	pub fn par_iter_with_ids(&self) -> impl ParallelIterator<Item = (FreeListEntryId, &T)> {
+		self.inner
+			.par_iter()
+			.enumerate()
+			.filter_map(|(slot, elem)| match elem {
+				Elem::Entry(id, item) => Some((FreeListEntryId(*id, slot), item)),
+				_ => None,
+			})
+	}
+}
+
+impl<T> Default for FreeList<T> {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl<T> Index<FreeListEntryId> for FreeList<T> {
 	type Output = T;
 	fn index(&self, index: FreeListEntryId) -> &Self::Output {
-		let FreeListEntryId(id, index) = index;
-
-		match &self.inner[index] {
-			Elem::Entry(eid, item) if id == *eid => item,
-			Elem::Entry(_, _) => panic!("Item no longer exists"),
-			_ => panic!("Attempted to access empty freelist slot."),
-		}
+		self.get(index).expect("Item no longer exists")
 	}
 }
 
 impl<T> IndexMut<FreeListEntryId> for FreeList<T> {
 	fn index_mut(&mut self, index: FreeListEntryId) -> &mut Self::Output {
-		let FreeListEntryId(id, index) = index;
-
-		match &mut self.inner[index] {
-			Elem::Entry(eid, item) if id == *eid => item,
-			Elem::Entry(_, _) => panic!("Item no longer exists"),
-			_ => panic!("Attempted to access empty freelist slot."),
-		}
+		self.get_mut(index).expect("Item no longer exists")
 	}
 }
 
@@ -178,3 +262,98 @@ enum Elem<T> {
 	NextFree(usize),
 	LastFree,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stale_ids_find_nothing() {
+		let mut list = FreeList::new();
+		let a = list.insert('a');
+		let b = list.insert('b');
+
+		assert_eq!(list.remove(a), Some('a'));
+		assert_eq!(list.get(a), None);
+		assert_eq!(list.get_mut(a), None);
+		assert_eq!(list.remove(a), None);
+
+		//The freed slot is reused, but not by the old id
+		let c = list.insert('c');
+		assert_eq!(list.slot_count(), 2);
+		assert_eq!(list.get(a), None);
+		assert_eq!(list.get(c), Some(&'c'));
+		assert_eq!(list.get(b), Some(&'b'));
+		assert_eq!(list.len(), 2);
+	}
+
+	#[test]
+	fn compact_remaps_live_ids() {
+		let mut list = FreeList::new();
+		let ids: Vec<_> = (0..10).map(|i| list.insert(i)).collect();
+		for &id in ids.iter().step_by(3) {
+			list.remove(id);
+		}
+
+		let live: Vec<_> = list.iter_with_ids().map(|(id, &i)| (id, i)).collect();
+		let remap = list.compact();
+
+		assert_eq!(list.slot_count(), list.len());
+		assert_eq!(remap.len(), live.len());
+		for (old, i) in live {
+			let new = remap[&old];
+			assert_eq!(list.get(new), Some(&i));
+			assert_eq!(list.get(old), None);
+		}
+
+		//Inserting after compacting appends, as there are no free slots
+		let id = list.insert(10);
+		assert_eq!(list.get(id), Some(&10));
+		assert_eq!(list.slot_count(), list.len());
+	}
+
+	#[test]
+	fn retain_and_drain_keep_len() {
+		let mut list = FreeList::new();
+		let ids: Vec<_> = (0..10).map(|i| list.insert(i)).collect();
+
+		list.retain(|&i| i % 2 == 0);
+		assert_eq!(list.len(), 5);
+		assert_eq!(list.iter().count(), 5);
+		assert!(ids
+			.iter()
+			.all(|&id| list.get(id).is_none_or(|i| i % 2 == 0)));
+
+		//Slots freed by retain are reused
+		list.insert(11);
+		assert_eq!(list.len(), 6);
+		assert_eq!(list.slot_count(), 10);
+
+		let mut drained: Vec<_> = list.drain().collect();
+		drained.sort();
+		assert_eq!(drained, vec![0, 2, 4, 6, 8, 11]);
+		assert_eq!(list.len(), 0);
+		assert!(list.is_empty());
+		assert_eq!(list.slot_count(), 0);
+		assert!(ids.iter().all(|&id| list.get(id).is_none()));
+	}
+
+	#[test]
+	fn ids_iterated_find_their_items() {
+		let mut list = FreeList::new();
+		let ids: Vec<_> = (0..6).map(|i| list.insert(i * 10)).collect();
+		list.remove(ids[2]);
+
+		let seen: Vec<_> = list.iter_with_ids().collect();
+		assert_eq!(seen.len(), list.len());
+		for (id, &item) in seen {
+			assert_eq!(list[id], item);
+			assert!(ids.contains(&id));
+		}
+
+		for (id, item) in list.iter_mut_with_ids() {
+			*item += id.1;
+		}
+		assert_eq!(list[ids[3]], 33);
+	}
+}
@@ -9,17 +9,21 @@ pub trait Automaton {
 
 	fn state(&self) -> Self::State;
 	fn state_mut(&mut self) -> &mut Self::State;
-	fn enter_from(&mut self, _old: Self::State) {}
-	fn exit_to(&mut self, _new: Self::State) {}
-	fn next_state(&self, external: &External) -> Self::State;
+	fn enter_from(&mut self, _old: Self::State, _external: &External) {}
+	fn exit_to(&mut self, _new: Self::State, _external: &External) {}
+	fn next_state(&mut self, external: &External) -> Self::State;
 
-	fn by_probability(&self, probability_table: &[(Self::State, f32)]) -> Self::State {
-		let mut rng = random();
+	//`roll` should be drawn uniformly from [0, 1)
+	fn by_probability(
+		&self,
+		mut roll: f32,
+		probability_table: &[(Self::State, f32)],
+	) -> Self::State {
 		for &(state, prob) in probability_table {
-			if rng < prob {
+			if roll < prob {
 				return state;
 			}
-			rng -= prob;
+			roll -= prob;
 		}
 		self.state()
 	}
@@ -75,9 +79,9 @@ impl<T: Automaton> GameObject for T {
 		let new = self.next_state(external);
 
 		if new != old {
-			self.exit_to(new);
+			self.exit_to(new, external);
 			*self.state_mut() = new;
-			self.enter_from(old);
+			self.enter_from(old, external);
 		}
 
 		self.fsm_update(external, messenger)
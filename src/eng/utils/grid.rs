@@ -2,15 +2,24 @@ use super::*;
 use fnv::*;
 use rayon::prelude::*;
 
-#[cfg(feature="profile")]
+#[cfg(feature = "profile")]
 use tracing::instrument;
 
 pub type GridId = FreeListEntryId;
 
+type Cell = (i32, i32);
+
 pub struct Grid<T: Griddable> {
 	scale: f32,
-	grid: FnvHashMap<(i32, i32), Vec<GridId>>,
-	elems: FreeList<T>,
+	grid: FnvHashMap<Cell, Vec<GridId>>,
+	elems: FreeList<Entry<T>>,
+}
+
+//Each element remembers the bucket it is filed under, which may lag
+//behind its position until the grid is maintained.
+struct Entry<T> {
+	cell: Cell,
+	item: T,
 }
 
 pub trait Griddable {
@@ -27,6 +36,10 @@ pub trait Griddable {
 	}
 }
 
+pub trait GriddableMut: Griddable {
+	fn set_pos(&mut self, pos: (f32, f32));
+}
+
 impl<T: Griddable> Grid<T> {
 	pub fn new(scale: f32) -> Self {
 		Self {
@@ -36,39 +49,54 @@ impl<T: Griddable> Grid<T> {
 		}
 	}
 
-	pub fn count(&self) -> usize {
-		self.elems.count()
+	pub fn len(&self) -> usize {
+		self.elems.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.elems.is_empty()
 	}
 
 	pub fn insert(&mut self, item: T) -> GridId {
 		let cell = Self::grid_cell(self.scale, item.pos());
-		let index = self.elems.insert(item);
+		let index = self.elems.insert(Entry { cell, item });
 		self.grid.entry(cell).or_default().push(index);
 		index
 	}
 
 	pub fn get(&self, index: GridId) -> Option<&T> {
-		self.elems.get(index)
+		self.elems.get(index).map(|entry| &entry.item)
 	}
 
+	//If the element is moved, it is only refiled once the grid is
+	//maintained or Grid::update_pos is called.
 	pub fn get_mut(&mut self, index: GridId) -> Option<&mut T> {
-		self.elems.get_mut(index)
+		self.elems.get_mut(index).map(|entry| &mut entry.item)
 	}
 
 	pub fn remove(&mut self, index: GridId) -> Option<T> {
-		let elem = self.elems.get(index)?;
+		let cell = self.elems.get(index)?.cell;
+		self.unfile(index, cell);
+		self.elems.remove(index).map(|entry| entry.item)
+	}
 
-		let pos = elem.pos();
+	//Refile an element whose position has changed. Returns false if the
+	//element no longer exists.
+	pub fn update_pos(&mut self, index: GridId) -> bool {
+		let Some(entry) = self.elems.get(index) else {
+			return false;
+		};
 
-		let v = self.grid.get_mut(&Self::grid_cell(self.scale, pos))?;
+		let old = entry.cell;
+		let new = Self::grid_cell(self.scale, entry.item.pos());
 
-		let (cell_i, &elem_i) = v
-			.iter()
-			.enumerate()
-			.find(|(_, &index)| self.elems[index].pos() == pos)?;
+		if old != new {
+			self.unfile(index, old);
+			self.grid.entry(new).or_default().push(index);
+			self.elems[index].cell = new;
+		}
 
-		v.swap_remove(cell_i);
-		self.elems.remove(elem_i)
+		true
 	}
 
 	pub fn nearest_by<M>(
@@ -104,25 +132,118 @@ impl<T: Griddable> Grid<T> {
 		radius: f32,
 	) -> impl Iterator<Item = (f32, (GridId, &T))> {
 		let Vector2 { x, y } = pos;
-		let (hi_x, hi_y) = Self::grid_cell(self.scale, (x + radius, y + radius));
-		let (lo_x, lo_y) = Self::grid_cell(self.scale, (x - radius, y - radius));
+		let reach = self.clamp_radius(pos, radius);
+		let hi = Self::grid_cell(self.scale, (x + reach, y + reach));
+		let lo = Self::grid_cell(self.scale, (x - reach, y - reach));
 
-		self.grid
-			.iter()
-			.filter(move |&((x, y), _)| (lo_x..=hi_x).contains(x) && (lo_y..=hi_y).contains(y))
-			.flat_map(|(_, v)| v.iter())
-			.map(move |&index| {
-				let item = &self.elems[index];
+		self.ids_in_cells(lo, hi)
+			.map(move |index| {
+				let item = &self.elems[index].item;
 				(dist(item.pos(), (x, y)), (index, item))
 			})
 			.filter(move |(d, (_id, i))| *d <= radius && i.alive())
 	}
 
+	//Elements inside the axis-aligned rectangle with corners `lo` & `hi`
+	pub fn query_rect(
+		&self,
+		lo: Vector2<f32>,
+		hi: Vector2<f32>,
+	) -> impl Iterator<Item = (GridId, &T)> {
+		let (lo, hi) = (
+			vec2(lo.x.min(hi.x), lo.y.min(hi.y)),
+			vec2(lo.x.max(hi.x), lo.y.max(hi.y)),
+		);
+
+		let lo_cell = Self::grid_cell(self.scale, lo.into());
+		let hi_cell = Self::grid_cell(self.scale, hi.into());
+
+		self.ids_in_cells(lo_cell, hi_cell)
+			.map(|index| (index, &self.elems[index].item))
+			.filter(move |(_, item)| {
+				let (x, y) = item.pos();
+				(lo.x..=hi.x).contains(&x) && (lo.y..=hi.y).contains(&y) && item.alive()
+			})
+	}
+
+	//Elements within `radius` of the segment from `a` to `b`, along with
+	//how far along the segment (0 to 1) each is closest to it.
+	pub fn query_segment(
+		&self,
+		a: Vector2<f32>,
+		b: Vector2<f32>,
+		radius: f32,
+	) -> impl Iterator<Item = (f32, (GridId, &T))> {
+		let ab = b - a;
+		let len2 = ab.magnitude2();
+
+		//Every element is at least as close to the segment as to a
+		let reach = self.clamp_radius(a, radius);
+
+		self.segment_cells(a, b, reach)
+			.into_iter()
+			.flat_map(|cell| self.grid.get(&cell).into_iter().flatten())
+			.filter_map(move |&index| {
+				let item = &self.elems[index].item;
+				let p = Vector2::from(item.pos());
+
+				let t = if len2 > 0. {
+					((p - a).dot(ab) / len2).clamp(0., 1.)
+				} else {
+					0.
+				};
+
+				let closest = a + t * ab;
+				(closest.distance(p) <= radius && item.alive()).then_some((t, (index, item)))
+			})
+	}
+
+	//First element within `radius` of the ray, and its distance along it
+	pub fn cast_ray(
+		&self,
+		origin: Vector2<f32>,
+		dir: Vector2<f32>,
+		max_dist: f32,
+		radius: f32,
+	) -> Option<(f32, (GridId, &T))> {
+		//Nothing lies further along the ray than the occupied cells reach
+		let max_dist = max_dist.min(self.clamp_radius(origin, max_dist) + radius);
+		let end = origin + unit_toward(vec2(0., 0.), dir) * max_dist;
+
+		self.query_segment(origin, end, radius)
+			.map(|(t, item)| (t * max_dist, item))
+			.min_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap())
+	}
+
+	//Up to k elements no further than max_dist from pos, nearest first
+	pub fn k_nearest(
+		&self,
+		pos: Vector2<f32>,
+		k: usize,
+		max_dist: f32,
+	) -> Vec<(f32, (GridId, &T))> {
+		//Widen the search until enough elements have been found, so that
+		//dense areas don't scan every cell within max_dist.
+		let reach = self.clamp_radius(pos, max_dist);
+		let mut radius = self.scale.min(max_dist);
+		loop {
+			let mut found = self.query_with_dist(pos, radius).collect::<Vec<_>>();
+
+			if found.len() >= k || radius >= max_dist || radius >= reach {
+				found.sort_by(|(d1, _), (d2, _)| d1.partial_cmp(d2).unwrap());
+				found.truncate(k);
+				return found;
+			}
+
+			radius = (2. * radius).min(max_dist);
+		}
+	}
+
 	pub fn retain<P: FnMut(&T) -> bool>(&mut self, mut predicate: P) {
 		for vec in self.grid.values_mut() {
 			let mut i = 0;
 			while i < vec.len() {
-				if !predicate(&self.elems[vec[i]]) {
+				if !predicate(&self.elems[vec[i]].item) {
 					self.elems.remove(vec[i]);
 					vec.swap_remove(i);
 				} else {
@@ -140,7 +261,7 @@ impl<T: Griddable> Grid<T> {
 		for (&bucket, vec) in &mut self.grid {
 			let mut i = 0;
 			while i < vec.len() {
-				let elem = &self.elems[vec[i]];
+				let elem = &self.elems[vec[i]].item;
 				let alive = elem.alive();
 				let cell = Self::grid_cell(self.scale, elem.pos());
 
@@ -157,6 +278,7 @@ impl<T: Griddable> Grid<T> {
 		}
 
 		for (cell, index) in moved {
+			self.elems[index].cell = cell;
 			self.grid.entry(cell).or_default().push(index);
 		}
 	}
@@ -165,12 +287,42 @@ impl<T: Griddable> Grid<T> {
 		self.grid.retain(|_, v| !v.is_empty());
 	}
 
+	//Invalidates every GridId handed out so far; the returned map gives
+	//each element's new id.
+	pub fn compact(&mut self) -> FnvHashMap<GridId, GridId> {
+		let remap = self.elems.compact();
+		for id in self.grid.values_mut().flatten() {
+			*id = remap[id];
+		}
+		remap
+	}
+
 	pub fn iter(&self) -> impl Iterator<Item = &T> {
-		self.elems.iter().filter(|e| e.alive())
+		self.elems
+			.iter()
+			.map(|entry| &entry.item)
+			.filter(|e| e.alive())
 	}
 
 	pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
-		self.elems.iter_mut().filter(|e| e.alive())
+		self.elems
+			.iter_mut()
+			.map(|entry| &mut entry.item)
+			.filter(|e| e.alive())
+	}
+
+	pub fn iter_with_ids(&self) -> impl Iterator<Item = (GridId, &T)> {
+		self.elems
+			.iter_with_ids()
+			.map(|(id, entry)| (id, &entry.item))
+			.filter(|(_, e)| e.alive())
+	}
+
+	pub fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (GridId, &mut T)> {
+		self.elems
+			.iter_mut_with_ids()
+			.map(|(id, entry)| (id, &mut entry.item))
+			.filter(|(_, e)| e.alive())
 	}
 
 	pub fn from_iter<I: Iterator<Item = T>>(scale: f32, iter: I) -> Self {
@@ -181,6 +333,118 @@ impl<T: Griddable> Grid<T> {
 		grid
 	}
 
+	fn unfile(&mut self, index: GridId, cell: Cell) {
+		let bucket = self
+			.grid
+			.get_mut(&cell)
+			.expect("Grid element filed under missing cell.");
+
+		let i = bucket
+			.iter()
+			.position(|&id| id == index)
+			.expect("Grid element missing from its cell.");
+
+		bucket.swap_remove(i);
+	}
+
+	//Ids in every cell from lo to hi, inclusive
+	fn ids_in_cells(
+		&self,
+		(lo_x, lo_y): (i32, i32),
+		(hi_x, hi_y): (i32, i32),
+	) -> impl Iterator<Item = GridId> + '_ {
+		let area = (hi_x as i64 - lo_x as i64 + 1).saturating_mul(hi_y as i64 - lo_y as i64 + 1);
+
+		//For large areas, scanning the occupied buckets beats probing
+		//mostly empty cells.
+		let scan = area > self.grid.len() as i64;
+
+		let probed = (!scan).then(|| {
+			(lo_x..=hi_x)
+				.flat_map(move |x| (lo_y..=hi_y).filter_map(move |y| self.grid.get(&(x, y))))
+		});
+
+		let scanned = scan.then(|| {
+			self.grid
+				.iter()
+				.filter(move |&((x, y), _)| (lo_x..=hi_x).contains(x) && (lo_y..=hi_y).contains(y))
+				.map(|(_, v)| v)
+		});
+
+		probed
+			.into_iter()
+			.flatten()
+			.chain(scanned.into_iter().flatten())
+			.flat_map(|v| v.iter().copied())
+	}
+
+	//Cells within `radius` of the segment from a to b, found a column at a time
+	fn segment_cells(&self, a: Vector2<f32>, b: Vector2<f32>, radius: f32) -> Vec<(i32, i32)> {
+		let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+
+		let y_at = |x: f32| {
+			if a.x == b.x {
+				(a.y.min(b.y), a.y.max(b.y))
+			} else {
+				let x = x.clamp(min_x, max_x);
+				let y = a.y + (x - a.x) * (b.y - a.y) / (b.x - a.x);
+				(y, y)
+			}
+		};
+
+		let (lo_col, _) = Self::grid_cell(self.scale, (min_x - radius, 0.));
+		let (hi_col, _) = Self::grid_cell(self.scale, (max_x + radius, 0.));
+
+		//Past as many cells as there are occupied ones, it is quicker to
+		//check every occupied cell instead
+		let occupied = self.grid.len() as i64;
+		let every_cell = || self.grid.keys().copied().collect();
+		if hi_col as i64 - lo_col as i64 + 1 > occupied {
+			return every_cell();
+		}
+
+		let mut cells = vec![];
+		for col in lo_col..=hi_col {
+			let x0 = col as f32 * self.scale;
+			let x1 = x0 + self.scale;
+
+			let (lo0, hi0) = y_at(x0 - radius);
+			let (lo1, hi1) = y_at(x1 + radius);
+
+			let (_, lo_row) = Self::grid_cell(self.scale, (0., lo0.min(lo1) - radius));
+			let (_, hi_row) = Self::grid_cell(self.scale, (0., hi0.max(hi1) + radius));
+
+			if (cells.len() as i64).saturating_add(hi_row as i64 - lo_row as i64 + 1) > occupied {
+				return every_cell();
+			}
+			cells.extend((lo_row..=hi_row).map(|row| (col, row)));
+		}
+
+		cells
+	}
+
+	//Radius no larger than needed to reach every occupied cell from pos,
+	//so that huge or infinite radii still give finite cell bounds. Only
+	//worked out when the radius spans more cells than are occupied.
+	fn clamp_radius(&self, pos: Vector2<f32>, radius: f32) -> f32 {
+		if radius <= self.scale * self.grid.len() as f32 {
+			return radius;
+		}
+
+		let furthest = self
+			.grid
+			.keys()
+			.map(|&(x, y)| {
+				let (x0, y0) = (x as f32 * self.scale, y as f32 * self.scale);
+				let dx = (pos.x - x0).abs().max((pos.x - x0 - self.scale).abs());
+				let dy = (pos.y - y0).abs().max((pos.y - y0 - self.scale).abs());
+				dx.hypot(dy)
+			})
+			.fold(0., f32::max);
+
+		radius.min(furthest)
+	}
+
 	fn grid_cell(scale: f32, (x, y): (f32, f32)) -> (i32, i32) {
 		debug_assert!(!x.is_nan() && !y.is_nan());
 		((x / scale).floor() as i32, (y / scale).floor() as i32)
@@ -188,7 +452,7 @@ impl<T: Griddable> Grid<T> {
 
 	pub fn dbg_analytics(&self) {
 		let cells = self.grid.len();
-		let len = self.elems.count();
+		let len = self.elems.len();
 		let max = self.grid.values().map(|v| v.len()).max();
 		let mean = self.grid.values().map(|v| v.len()).sum::<usize>() as f32 / cells as f32;
 		dbg!(cells);
@@ -198,6 +462,17 @@ impl<T: Griddable> Grid<T> {
 	}
 }
 
+impl<T: GriddableMut> Grid<T> {
+	pub fn move_to(&mut self, index: GridId, pos: Vector2<f32>) -> bool {
+		match self.get_mut(index) {
+			Some(item) => item.set_pos(pos.into()),
+			None => return false,
+		}
+
+		self.update_pos(index)
+	}
+}
+
 impl<T, S, A> GameObject for Grid<T>
 where
 	S: Root,
@@ -256,7 +531,7 @@ impl<T: Griddable + Send + Sync> Grid<T> {
 				let mut i = 0;
 				while i < vec.len() {
 					let lock = elems.read().unwrap();
-					let elem = &lock[vec[i]];
+					let elem = &lock[vec[i]].item;
 					let alive = elem.alive();
 					let cell = Self::grid_cell(self.scale, elem.pos());
 					std::mem::drop(lock);
@@ -275,15 +550,289 @@ impl<T: Griddable + Send + Sync> Grid<T> {
 			});
 
 		for (cell, index) in moved_r.iter() {
+			self.elems[index].cell = cell;
 			self.grid.entry(cell).or_default().push(index);
 		}
 	}
 
 	pub fn par_iter(&self) -> impl ParallelIterator<Item = &T> {
-		self.elems.par_iter()
+		self.elems.par_iter().map(|entry| &entry.item)
 	}
 
 	pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut T> {
-		self.elems.par_iter_mut()
+		self.elems.par_iter_mut().map(|entry| &mut entry.item)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Clone, Debug, PartialEq)]
+	struct Point {
+		pos: (f32, f32),
+		alive: bool,
+	}
+
+	impl Griddable for Point {
+		fn alive(&self) -> bool {
+			self.alive
+		}
+
+		fn pos(&self) -> (f32, f32) {
+			self.pos
+		}
+	}
+
+	impl GriddableMut for Point {
+		fn set_pos(&mut self, pos: (f32, f32)) {
+			self.pos = pos;
+		}
+	}
+
+	const SCALE: f32 = 10.;
+	const EXTENT: f32 = 100.;
+
+	//Brute force model of a grid: every element, scanned in full
+	#[derive(Default)]
+	struct Reference {
+		elems: Vec<(GridId, Point)>,
+	}
+
+	impl Reference {
+		fn live(&self) -> impl Iterator<Item = &(GridId, Point)> {
+			self.elems.iter().filter(|(_, point)| point.alive)
+		}
+
+		fn point_mut(&mut self, id: GridId) -> &mut Point {
+			&mut self.elems.iter_mut().find(|(i, _)| *i == id).unwrap().1
+		}
+	}
+
+	fn ids<'a>(items: impl Iterator<Item = (GridId, &'a Point)>) -> FnvHashSet<GridId> {
+		items.map(|(id, _)| id).collect()
+	}
+
+	fn random_pos(rng: &mut Rng) -> (f32, f32) {
+		rng.rand_in2d(-EXTENT, EXTENT).into()
+	}
+
+	fn check(grid: &Grid<Point>, reference: &Reference, rng: &mut Rng) {
+		for (id, point) in &reference.elems {
+			assert_eq!(grid.get(*id), Some(point));
+		}
+
+		let pos = vec2(rng.rand_in(-EXTENT, EXTENT), rng.rand_in(-EXTENT, EXTENT));
+		let radius = rng.rand_in(0., EXTENT);
+
+		let expected = reference
+			.live()
+			.filter(|(_, point)| dist(point.pos, pos.into()) <= radius)
+			.map(|(id, _)| *id)
+			.collect();
+		assert_eq!(ids(grid.query_at(pos, radius)), expected);
+
+		let corner = pos + vec2(rng.rand_in(-EXTENT, EXTENT), rng.rand_in(-EXTENT, EXTENT));
+		let (lo, hi) = (
+			vec2(pos.x.min(corner.x), pos.y.min(corner.y)),
+			vec2(pos.x.max(corner.x), pos.y.max(corner.y)),
+		);
+		let expected = reference
+			.live()
+			.filter(|(_, Point { pos: (x, y), .. })| {
+				(lo.x..=hi.x).contains(x) && (lo.y..=hi.y).contains(y)
+			})
+			.map(|(id, _)| *id)
+			.collect();
+		assert_eq!(ids(grid.query_rect(pos, corner)), expected);
+
+		//Ties may be broken either way, so only distances are compared
+		let k = (rng.random() * 8.) as usize;
+		let mut expected: Vec<_> = reference
+			.live()
+			.map(|(_, point)| dist(point.pos, pos.into()))
+			.filter(|&d| d <= radius)
+			.collect();
+		expected.sort_by(f32::total_cmp);
+		expected.truncate(k);
+		let found: Vec<_> = grid
+			.k_nearest(pos, k, radius)
+			.into_iter()
+			.map(|(d, _)| d)
+			.collect();
+		assert_eq!(found, expected);
+
+		//Segments in any direction, some of them no length at all
+		let end = match rng.random() {
+			r if r < 0.2 => pos,
+			r if r < 0.4 => pos + vec2(1., 1.) * rng.rand_in(-EXTENT, EXTENT),
+			_ => random_pos(rng).into(),
+		};
+		let near = rng.rand_in(0., SCALE * 2.);
+		let expected = reference
+			.live()
+			.filter(|(_, point)| segment_dist(pos, end, point.pos.into()).1 <= near)
+			.map(|(id, _)| *id)
+			.collect();
+		let found = grid.query_segment(pos, end, near).map(|(_, item)| item);
+		assert_eq!(ids(found), expected);
+
+		//Rays sometimes start right on top of an element
+		let origin = match reference.live().next() {
+			Some((_, point)) if rng.probability(0.3) => point.pos.into(),
+			_ => pos,
+		};
+		let dir = vec2(rng.rand_in(-1., 1.), rng.rand_in(-1., 1.));
+		let expected = reference
+			.live()
+			.map(|(_, point)| {
+				segment_dist(origin, origin + dir.normalize() * radius, point.pos.into())
+			})
+			.filter(|&(_, d)| d <= near)
+			.map(|(t, _)| t * radius)
+			.min_by(f32::total_cmp);
+		let found = grid.cast_ray(origin, dir, radius, near).map(|(d, _)| d);
+		match (found, expected) {
+			(Some(found), Some(expected)) => assert!((found - expected).abs() < 1e-3),
+			(found, expected) => assert_eq!(found, expected),
+		}
+	}
+
+	//How far along the segment from a to b, 0 to 1, is closest to p, and
+	//how far p is from there
+	fn segment_dist(a: Vector2<f32>, b: Vector2<f32>, p: Vector2<f32>) -> (f32, f32) {
+		let ab = b - a;
+		let len2 = ab.magnitude2();
+		let t = if len2 > 0. {
+			((p - a).dot(ab) / len2).clamp(0., 1.)
+		} else {
+			0.
+		};
+		(t, (a + t * ab).distance(p))
+	}
+
+	#[test]
+	fn matches_brute_force() {
+		for seed in 0..20 {
+			let mut rng = Rng::new(seed);
+			let mut grid = Grid::new(SCALE);
+			let mut reference = Reference::default();
+			//Elements moved without being refiled, so queries may be stale
+			let mut stale = false;
+
+			for _ in 0..500 {
+				let existing = (!reference.elems.is_empty()).then(|| {
+					let i = (rng.random() * reference.elems.len() as f32) as usize;
+					reference.elems[i.min(reference.elems.len() - 1)].0
+				});
+
+				match ((rng.random() * 8.) as u32, existing) {
+					(0 | 1, _) | (_, None) => {
+						//Stack some elements on top of existing ones
+						let pos = match existing {
+							Some(id) if rng.probability(0.3) => reference.point_mut(id).pos,
+							_ => random_pos(&mut rng),
+						};
+						let point = Point { pos, alive: true };
+						let id = grid.insert(point.clone());
+						reference.elems.push((id, point));
+					}
+					(2, Some(id)) => {
+						let pos = random_pos(&mut rng);
+						grid.get_mut(id).unwrap().pos = pos;
+						reference.point_mut(id).pos = pos;
+						assert!(grid.update_pos(id));
+					}
+					(3, Some(id)) => {
+						let pos = random_pos(&mut rng);
+						reference.point_mut(id).pos = pos;
+						assert!(grid.move_to(id, pos.into()));
+					}
+					(4, Some(id)) => {
+						let i = reference.elems.iter().position(|(i, _)| *i == id).unwrap();
+						let (_, point) = reference.elems.swap_remove(i);
+						assert_eq!(grid.remove(id), Some(point));
+						assert_eq!(grid.remove(id), None);
+						assert!(!grid.update_pos(id));
+					}
+					(5, Some(id)) => {
+						let pos = random_pos(&mut rng);
+						grid.get_mut(id).unwrap().pos = pos;
+						reference.point_mut(id).pos = pos;
+						stale = true;
+					}
+					(6, Some(id)) => {
+						grid.get_mut(id).unwrap().alive = false;
+						reference.point_mut(id).alive = false;
+					}
+					_ => {
+						grid.maintain();
+						for (id, point) in &reference.elems {
+							if !point.alive {
+								assert!(grid.get(*id).is_none());
+							}
+						}
+						reference.elems.retain(|(_, point)| point.alive);
+						stale = false;
+					}
+				}
+
+				if !stale {
+					check(&grid, &reference, &mut rng);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn infinite_radius() {
+		let mut grid = Grid::new(SCALE);
+		let all: FnvHashSet<_> = [(0., 0.), (-45., 12.), (80., -3.)]
+			.into_iter()
+			.map(|pos| grid.insert(Point { pos, alive: true }))
+			.collect();
+
+		let pos = vec2(5., 5.);
+		let inf = f32::INFINITY;
+		assert_eq!(ids(grid.query_at(pos, inf)), all);
+		assert_eq!(grid.k_nearest(pos, 10, inf).len(), all.len());
+		assert_eq!(ids(grid.query_rect(vec2(-inf, -inf), vec2(inf, inf))), all);
+		let found = grid
+			.query_segment(pos, vec2(1e30, 5.), inf)
+			.map(|(_, item)| item);
+		assert_eq!(ids(found), all);
+
+		let (d, (_, point)) = grid
+			.cast_ray(vec2(-100., -3.), vec2(1., 0.), inf, 1.)
+			.unwrap();
+		assert_eq!(point.pos, (80., -3.));
+		assert!((d - 180.).abs() < 1e-3);
+	}
+
+	#[test]
+	fn elements_at_same_position() {
+		let mut grid = Grid::new(SCALE);
+		let point = Point {
+			pos: (5., 5.),
+			alive: true,
+		};
+
+		let a = grid.insert(point.clone());
+		let b = grid.insert(point.clone());
+
+		let near = |grid: &Grid<Point>| ids(grid.query_at(vec2(5., 5.), 1.));
+		assert_eq!(near(&grid), [a, b].into_iter().collect());
+
+		//Moving one away must leave the other filed where it was
+		assert!(grid.move_to(b, vec2(50., 50.)));
+		assert_eq!(near(&grid), [a].into_iter().collect());
+
+		assert!(grid.move_to(b, vec2(5., 5.)));
+		assert_eq!(grid.remove(a), Some(point.clone()));
+		assert_eq!(near(&grid), [b].into_iter().collect());
+		assert_eq!(grid.get(b), Some(&point));
+
+		assert_eq!(grid.remove(b), Some(point));
+		assert!(near(&grid).is_empty());
 	}
 }
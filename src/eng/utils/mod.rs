@@ -6,6 +6,7 @@ mod freelist;
 mod fsm;
 mod grid;
 mod relaxed;
+mod rng;
 mod task;
 //pub mod ui;
 
@@ -14,6 +15,7 @@ pub use freelist::*;
 pub use fsm::Automaton;
 pub use grid::*;
 pub use relaxed::*;
+pub use rng::*;
 pub use task::*;
 
 pub fn unit_in_dir(deg: f32) -> Vector2<f32> {
@@ -37,22 +39,6 @@ pub fn unit_toward(from: Vector2<f32>, to: Vector2<f32>) -> Vector2<f32> {
 	}
 }
 
-pub fn probability(p: f32) -> bool {
-	random() < p
-}
-
-pub fn random() -> f32 {
-	rand::random::<f32>()
-}
-
-pub fn rand_in(lo: f32, hi: f32) -> f32 {
-	lo + (hi - lo) * random()
-}
-
-pub fn rand_in2d(lo: f32, hi: f32) -> Vector2<f32> {
-	vec2(rand_in(lo, hi), rand_in(lo, hi))
-}
-
 pub fn snap_to_grid(p: Vector2<f32>, (cellx, celly): (f32, f32)) -> Vector2<i32> {
 	vec2(
 		(cellx * (p.x / cellx).round()) as i32,
@@ -0,0 +1,62 @@
+//Seedable source of randomness. Every random decision in a world should be
+//drawn from one of these so that a run can be reproduced from its seed.
+//ChaCha8 is used as its output is portable, unlike StdRng's, which may
+//change between releases of rand.
+
+use cgmath::*;
+use rand::{Rng as _, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::hash::{Hash, Hasher};
+
+//Drawing needs mutable access, so rather than sharing one, each subsystem
+//should keep its own stream.
+#[derive(Clone)]
+pub struct Rng {
+	seed: u64,
+	inner: ChaCha8Rng,
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			seed,
+			inner: ChaCha8Rng::seed_from_u64(seed),
+		}
+	}
+
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	//Independent stream identified by `key`. Depends only on this rng's seed
+	//and the key, not on how many numbers have been drawn, so subsystems
+	//(e.g. chunk generation on other threads) stay deterministic.
+	pub fn stream(&self, key: impl Hash) -> Self {
+		let mut hasher = fnv::FnvHasher::default();
+		self.seed.hash(&mut hasher);
+		key.hash(&mut hasher);
+		Self::new(hasher.finish())
+	}
+
+	//Independent stream for something created from this one, e.g. a spawned
+	//object that makes its own random decisions
+	pub fn split(&mut self) -> Self {
+		Self::new(self.inner.next_u64())
+	}
+
+	pub fn probability(&mut self, p: f32) -> bool {
+		self.random() < p
+	}
+
+	pub fn random(&mut self) -> f32 {
+		self.inner.gen::<f32>()
+	}
+
+	pub fn rand_in(&mut self, lo: f32, hi: f32) -> f32 {
+		lo + (hi - lo) * self.random()
+	}
+
+	pub fn rand_in2d(&mut self, lo: f32, hi: f32) -> Vector2<f32> {
+		vec2(self.rand_in(lo, hi), self.rand_in(lo, hi))
+	}
+}
@@ -1,15 +1,21 @@
 //A simple struct to offload a computation to another thread
-//and block until it is complete
+//and block until it is complete. Tasks wait in a shared queue and run
+//highest priority first, with at most one per worker thread at a time.
 
 use std::cell::OnceCell;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use std::sync::mpsc::*;
+use std::sync::{Arc, Mutex};
 
 pub struct Task<T: Send + 'static> {
 	result: OnceCell<T>,
 	recv: Option<Receiver<T>>,
+	job: Option<Arc<Job>>,
 }
 
 impl<T: Send + 'static> Task<T> {
+	pub const DEFAULT_PRIORITY: f32 = 0.;
+
 	//Construct without launching thread
 	pub fn from_val(val: T) -> Self {
 		let cell = OnceCell::new();
@@ -19,19 +25,46 @@ impl<T: Send + 'static> Task<T> {
 		Self {
 			result: cell,
 			recv: None,
+			job: None,
 		}
 	}
 
 	pub fn launch(f: impl FnOnce() -> T + Send + 'static) -> Self {
+		Self::launch_with_priority(Self::DEFAULT_PRIORITY, f)
+	}
+
+	//Higher priority tasks are started first
+	pub fn launch_with_priority(priority: f32, f: impl FnOnce() -> T + Send + 'static) -> Self {
 		let (sender, receiver) = channel();
 
-		rayon::spawn(move || {
-			let _ = sender.send(f());
+		let job = Arc::new(Job {
+			work: Mutex::new(Some(Box::new(move || {
+				let _ = sender.send(f());
+			}))),
+			priority: AtomicU32::new(priority.to_bits()),
+			cancelled: AtomicBool::new(false),
 		});
 
+		SCHEDULER.submit(job.clone());
+
 		Self {
 			result: OnceCell::new(),
 			recv: Some(receiver),
+			job: Some(job),
+		}
+	}
+
+	pub fn set_priority(&self, priority: f32) {
+		if let Some(job) = &self.job {
+			job.priority.store(priority.to_bits(), Ordering::Relaxed);
+		}
+	}
+
+	//Stops the task from starting in the background. It still runs if
+	//its result is asked for with get. Dropping a task cancels it too.
+	pub fn cancel(&self) {
+		if let Some(job) = &self.job {
+			job.cancelled.store(true, Ordering::Relaxed);
 		}
 	}
 
@@ -57,6 +90,7 @@ impl<T: Send + 'static> Task<T> {
 
 	pub fn get(&self) -> &T {
 		if self.result.get().is_none() {
+			self.run_here();
 			let result = self.recv.as_ref().unwrap().recv().unwrap();
 			let _ = self.result.set(result);
 		}
@@ -66,9 +100,101 @@ impl<T: Send + 'static> Task<T> {
 
 	pub fn get_mut(&mut self) -> &mut T {
 		if self.result.get().is_none() {
+			self.run_here();
 			let result = self.recv.as_ref().unwrap().recv().unwrap();
 			let _ = self.result.set(result);
 		}
 		self.result.get_mut().unwrap()
 	}
+
+	//Runs the task on this thread if it hasn't started yet, rather than
+	//waiting for its turn
+	fn run_here(&self) {
+		if let Some(work) = self.job.as_ref().and_then(|job| job.take()) {
+			work();
+		}
+	}
+}
+
+impl<T: Send + 'static> Drop for Task<T> {
+	fn drop(&mut self) {
+		self.cancel();
+	}
+}
+
+struct Job {
+	work: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+	priority: AtomicU32,
+	cancelled: AtomicBool,
+}
+
+impl Job {
+	fn take(&self) -> Option<Box<dyn FnOnce() + Send>> {
+		self.work.lock().unwrap().take()
+	}
+
+	fn priority(&self) -> f32 {
+		f32::from_bits(self.priority.load(Ordering::Relaxed))
+	}
+
+	//Still worth running in the background
+	fn waiting(&self) -> bool {
+		!self.cancelled.load(Ordering::Relaxed) && self.work.lock().unwrap().is_some()
+	}
+}
+
+static SCHEDULER: Scheduler = Scheduler {
+	queue: Mutex::new(vec![]),
+	in_flight: AtomicUsize::new(0),
+};
+
+struct Scheduler {
+	queue: Mutex<Vec<Arc<Job>>>,
+	in_flight: AtomicUsize,
+}
+
+impl Scheduler {
+	fn submit(&'static self, job: Arc<Job>) {
+		self.queue.lock().unwrap().push(job);
+		self.pump();
+	}
+
+	//Starts queued jobs until every worker thread is busy
+	fn pump(&'static self) {
+		let max_in_flight = rayon::current_num_threads();
+
+		loop {
+			if self.in_flight.fetch_add(1, Ordering::AcqRel) >= max_in_flight {
+				self.in_flight.fetch_sub(1, Ordering::AcqRel);
+				return;
+			}
+
+			let Some(job) = self.next() else {
+				self.in_flight.fetch_sub(1, Ordering::AcqRel);
+				return;
+			};
+
+			rayon::spawn(move || {
+				if let Some(work) = job.take() {
+					work();
+				}
+				self.in_flight.fetch_sub(1, Ordering::AcqRel);
+				self.pump();
+			});
+		}
+	}
+
+	//Highest priority job still waiting, dropping any that aren't
+	fn next(&self) -> Option<Arc<Job>> {
+		let mut queue = self.queue.lock().unwrap();
+		queue.retain(|job| job.waiting());
+
+		let best = queue
+			.iter()
+			.enumerate()
+			.max_by(|(_, a), (_, b)| a.priority().total_cmp(&b.priority()))
+			.map(|(i, _)| i)?;
+
+		Some(queue.swap_remove(best))
+	}
 }
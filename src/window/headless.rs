@@ -0,0 +1,61 @@
+//Stands in for the renderer when there is no display. Rather than drawing,
+//it keeps what was queued since the last clear so it can be inspected.
+
+use super::*;
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct Recording {
+	queued: Vec<Instance>,
+	cached_draws: usize,
+	cached_instances: usize,
+	caches: fnv::FnvHashMap<CacheId, usize>,
+	cached_count: usize,
+}
+
+impl Recording {
+	//Uncached instances queued this frame
+	pub fn queued(&self) -> &[Instance] {
+		&self.queued
+	}
+
+	//Number of cached buffers drawn this frame
+	pub fn cached_draws(&self) -> usize {
+		self.cached_draws
+	}
+
+	//Total instances drawn this frame, cached or otherwise
+	pub fn instance_count(&self) -> usize {
+		self.queued.len() + self.cached_instances
+	}
+
+	pub(super) fn reserve(&mut self, n: usize) {
+		self.queued.reserve(n);
+	}
+
+	pub(super) fn clear(&mut self) {
+		self.queued.clear();
+		self.cached_draws = 0;
+		self.cached_instances = 0;
+	}
+
+	pub(super) fn queue(&mut self, instance: Instance) {
+		self.queued.push(instance);
+	}
+
+	pub(super) fn cache(&mut self, instances: &[Instance]) -> CacheId {
+		let id = CacheId::new(self.cached_count);
+		self.cached_count += 1;
+		self.caches.insert(id.clone(), instances.len());
+		id
+	}
+
+	pub(super) fn queue_cached(&mut self, id: CacheId) {
+		self.cached_draws += 1;
+		self.cached_instances += self.caches[&id];
+	}
+
+	pub(super) fn clean_cache(&mut self) {
+		self.caches.retain(|k, _| Arc::strong_count(k) > 1);
+	}
+}
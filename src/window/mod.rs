@@ -1,16 +1,16 @@
 #![allow(dead_code)]
 
 pub mod glsl;
+mod headless;
 mod loader;
 mod reng;
 mod types;
 
 pub use glsl::*;
+pub use headless::*;
 pub use reng::CacheId;
 pub use types::*;
 
-use std::time::Instant;
-
 #[cfg(feature = "profile")]
 use tracing::instrument;
 
@@ -20,15 +20,24 @@ const START_WIN_SIZE: winit::dpi::PhysicalSize<f32> = winit::dpi::PhysicalSize {
 };
 
 pub struct Window {
-	window: winit::window::Window,
+	backend: Backend,
 	inputs: External,
-	renderer: reng::Renderer<glsl::Uniform, Instance>,
+	title: String,
+}
+
+enum Backend {
+	Surface {
+		window: winit::window::Window,
+		renderer: Box<reng::Renderer<glsl::Uniform, Instance>>,
+	},
+	Headless(Recording),
 }
 
 impl Window {
 	pub fn new<Texture: TextureType>(
 		title: &'static str,
 		event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+		seed: u64,
 	) -> Self {
 		let window = winit::window::WindowBuilder::new()
 			.with_min_inner_size(START_WIN_SIZE)
@@ -38,30 +47,28 @@ impl Window {
 
 		let size = window.inner_size();
 
-		let mut renderer = reng::Renderer::new(&window, 4);
+		let mut renderer = Box::new(reng::Renderer::new(&window, 4));
 
 		let (image, texture_map) = loader::load_textures::<Texture>();
 		let texture = renderer.create_texture_from_image(&image);
 		renderer.set_texture(&texture);
 
 		Self {
-			window,
-			renderer,
-			inputs: External {
-				scroll: 0.,
-				mouse_pos: cgmath::vec2(0.0, 0.0),
-				left_mouse: ButtonState::Up,
-				right_mouse: ButtonState::Up,
-				keymap: fnv::FnvHashMap::default(),
-				texture_map,
-				camera: Camera {
-					pos: cgmath::vec2(0., 0.),
-					scale: 600.,
-				},
-				win_size: (size.width, size.height),
-				now: Instant::now(),
-				delta: 0.,
-			},
+			backend: Backend::Surface { window, renderer },
+			inputs: External::new(texture_map, (size.width, size.height), seed),
+			title: title.to_string(),
+		}
+	}
+
+	//No window or GPU surface is created; queued instances are recorded
+	//instead of drawn, so worlds can be stepped in tests or without a display.
+	pub fn headless<Texture: TextureType>(win_size: (u32, u32), seed: u64) -> Self {
+		let (_image, texture_map) = loader::load_textures::<Texture>();
+
+		Self {
+			backend: Backend::Headless(Recording::default()),
+			inputs: External::new(texture_map, win_size, seed),
+			title: String::new(),
 		}
 	}
 
@@ -73,38 +80,83 @@ impl Window {
 		&self.inputs
 	}
 
+	pub fn title(&self) -> &str {
+		&self.title
+	}
+
+	//Only touches the window when the title changes, so it can be set
+	//every frame
+	pub fn set_title(&mut self, title: &str) {
+		if self.title != title {
+			self.title = title.to_string();
+			if let Backend::Surface { window, .. } = &self.backend {
+				window.set_title(title);
+			}
+		}
+	}
+
+	//None if this window draws to a surface
+	pub fn recording(&self) -> Option<&Recording> {
+		match &self.backend {
+			Backend::Headless(recording) => Some(recording),
+			Backend::Surface { .. } => None,
+		}
+	}
+
 	//Optional optimization
 	pub fn reserve(&mut self, n: usize) {
-		self.renderer.reserve(n);
+		match &mut self.backend {
+			Backend::Surface { renderer, .. } => renderer.reserve(n),
+			Backend::Headless(recording) => recording.reserve(n),
+		}
 	}
 
 	pub fn clear(&mut self) {
-		//Red for debugging purposes.
-		self.renderer.clear(wgpu::Color::RED);
+		match &mut self.backend {
+			//Red for debugging purposes.
+			Backend::Surface { renderer, .. } => renderer.clear(wgpu::Color::RED),
+			Backend::Headless(recording) => recording.clear(),
+		}
 	}
 
 	pub fn queue(&mut self, instance: Instance) {
 		//clip unseen instances
 		if self.inputs.visible(instance) {
-			self.renderer.queue(instance);
+			match &mut self.backend {
+				Backend::Surface { renderer, .. } => renderer.queue(instance),
+				Backend::Headless(recording) => recording.queue(instance),
+			}
 		}
 	}
 
 	pub fn cache(&mut self, instances: &[Instance]) -> CacheId {
-		self.renderer.cache(instances)
+		match &mut self.backend {
+			Backend::Surface { renderer, .. } => renderer.cache(instances),
+			Backend::Headless(recording) => recording.cache(instances),
+		}
 	}
 
 	pub fn queue_cached(&mut self, id: &CacheId) {
 		let id = id.clone();
-		self.renderer.queue_cached(id);
+		match &mut self.backend {
+			Backend::Surface { renderer, .. } => renderer.queue_cached(id),
+			Backend::Headless(recording) => recording.queue_cached(id),
+		}
 	}
 
 	pub fn clean_cache(&mut self) {
-		self.renderer.clean_cache();
+		match &mut self.backend {
+			Backend::Surface { renderer, .. } => renderer.clean_cache(),
+			Backend::Headless(recording) => recording.clean_cache(),
+		}
 	}
 
 	#[cfg_attr(feature = "profile", instrument(skip_all, name = "Presenting"))]
 	pub fn submit(&mut self) {
+		let Backend::Surface { renderer, .. } = &mut self.backend else {
+			return;
+		};
+
 		let uniform = glsl::Uniform {
 			ortho: Camera {
 				pos: self.inputs.camera.pos,
@@ -113,15 +165,25 @@ impl Window {
 			.proj(self.inputs.aspect()),
 		};
 
-		self.renderer.flush(uniform);
+		renderer.flush(uniform);
 	}
 
-	pub fn resize(&mut self, dims: winit::dpi::PhysicalSize<u32>) {
-		self.inputs.win_size = (dims.width, dims.height);
-		self.renderer.resize(dims);
+	//Resizing only changes the size the game sees. The surface is resized
+	//separately, as it must follow the real window even during replays.
+	pub fn handle(&mut self, event: InputEvent) {
+		self.inputs.capture(event);
 	}
 
-	pub fn id(&self) -> winit::window::WindowId {
-		self.window.id()
+	pub fn resize_surface(&mut self, dims: winit::dpi::PhysicalSize<u32>) {
+		if let Backend::Surface { renderer, .. } = &mut self.backend {
+			renderer.resize(dims);
+		}
+	}
+
+	pub fn id(&self) -> Option<winit::window::WindowId> {
+		match &self.backend {
+			Backend::Surface { window, .. } => Some(window.id()),
+			Backend::Headless(_) => None,
+		}
 	}
 }
@@ -1,6 +1,7 @@
 use super::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Camera {
 	pub pos: Vector2<f32>,
 	pub scale: f32,
@@ -18,6 +19,13 @@ impl Camera {
 		)
 	}
 
+	pub fn lerp(self, other: Self, t: f32) -> Self {
+		Self {
+			pos: self.pos.lerp(other.pos, t),
+			scale: self.scale + t * (other.scale - self.scale),
+		}
+	}
+
 	pub fn screen_to_world(&self, p: Vector2<f32>) -> Vector2<f32> {
 		self.scale * p + self.pos
 	}
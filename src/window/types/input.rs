@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+use winit::event::*;
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ButtonState {
 	Up,
@@ -29,9 +32,9 @@ impl ButtonState {
 	pub fn pressed(&self) -> bool {
 		*self == ButtonState::Pressed
 	}
-	
+
 	pub fn released(&self) -> bool {
-		*self == ButtonState::Released		
+		*self == ButtonState::Released
 	}
 
 	pub fn is_down(&self) -> bool {
@@ -42,3 +45,46 @@ impl ButtonState {
 		}
 	}
 }
+
+//Everything the game learns from the window, in a form that can be
+//recorded and fed back in later.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum InputEvent {
+	Key(VirtualKeyCode, bool),
+	MouseButton(MouseButton, bool),
+	CursorMoved(f64, f64),
+	Scroll(f32),
+	Resized(u32, u32),
+}
+
+impl InputEvent {
+	pub fn from_winit(event: &WindowEvent) -> Option<Self> {
+		use InputEvent::*;
+		match event {
+			WindowEvent::KeyboardInput { input, .. } => input
+				.virtual_keycode
+				.map(|key| Key(key, input.state == ElementState::Pressed)),
+
+			WindowEvent::MouseInput { button, state, .. } => {
+				Some(MouseButton(*button, *state == ElementState::Pressed))
+			}
+
+			WindowEvent::CursorMoved { position, .. } => Some(CursorMoved(position.x, position.y)),
+
+			WindowEvent::MouseWheel { delta, .. } => {
+				use winit::dpi::PhysicalPosition;
+				use winit::event::MouseScrollDelta::*;
+				Some(Scroll(match delta {
+					LineDelta(_hor, ver) => *ver,
+					PixelDelta(PhysicalPosition { y, .. }) => *y as f32,
+				}))
+			}
+
+			WindowEvent::Resized(dims) if dims.height != 0 && dims.width != 0 => {
+				Some(Resized(dims.width, dims.height))
+			}
+
+			_ => None,
+		}
+	}
+}
@@ -9,6 +9,7 @@ pub use instance::*;
 pub use texture::*;
 
 use super::glsl::*;
+use crate::eng::Rng;
 use cgmath::*;
 use std::time::Instant;
 use winit::event::*;
@@ -27,9 +28,39 @@ pub struct External {
 	pub camera: Camera,
 	pub now: Instant,
 	pub delta: f32,
+	//Seeds the world. Subsystems take their own streams from it to draw on.
+	pub rng: Rng,
+	//Fraction of a tick elapsed since the last simulation step. Used
+	//when rendering to interpolate between the last two states. While
+	//drawing, now and camera are already interpolated.
+	pub alpha: f32,
+	//Set while inputs are recorded or played back. Anything read from
+	//outside the game then, like a quicksave, could differ on playback.
+	pub recording: bool,
 }
 
 impl External {
+	pub fn new(texture_map: TextureMap, win_size: (u32, u32), seed: u64) -> Self {
+		Self {
+			scroll: 0.,
+			mouse_pos: vec2(0.0, 0.0),
+			left_mouse: ButtonState::Up,
+			right_mouse: ButtonState::Up,
+			keymap: fnv::FnvHashMap::default(),
+			texture_map,
+			camera: Camera {
+				pos: vec2(0., 0.),
+				scale: 600.,
+			},
+			win_size,
+			now: Instant::now(),
+			delta: 0.,
+			rng: Rng::new(seed),
+			alpha: 0.,
+			recording: false,
+		}
+	}
+
 	pub fn update(&mut self, now: Instant) {
 		self.delta = now.duration_since(self.now).as_secs_f32();
 		self.now = now;
@@ -126,13 +157,29 @@ impl External {
 			..
 		} = input;
 		if let Some(key) = key {
-			let down = state == ElementState::Pressed;
+			self.key_input(key, state == ElementState::Pressed);
+		}
+	}
+
+	pub fn key_input(&mut self, key: VirtualKeyCode, down: bool) {
+		if let Some(button) = self.keymap.get_mut(&key) {
+			button.update(down);
+		} else {
+			self.keymap.insert(key, ButtonState::new(down));
+		}
+	}
 
-			if let Some(button) = self.keymap.get_mut(&key) {
-				button.update(down);
-			} else {
-				self.keymap.insert(key, ButtonState::new(down));
+	pub fn capture(&mut self, event: InputEvent) {
+		use InputEvent::*;
+		match event {
+			Key(key, down) => self.key_input(key, down),
+			MouseButton(button, down) => self.mouse_button(&button, down),
+			CursorMoved(x, y) => {
+				let position = winit::dpi::PhysicalPosition::new(x, y);
+				self.capture_mouse(&position, self.win_size)
 			}
+			Scroll(scroll) => self.scroll = scroll,
+			Resized(width, height) => self.win_size = (width, height),
 		}
 	}
 
@@ -0,0 +1,336 @@
+use super::*;
+use crate::eng::*;
+use crate::window::*;
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Boat {
+	pub kind: BoatKind,
+	pub pos: Vector2<f32>,
+	pub dir: Vector2<f32>,
+	//Position before the latest update, for render interpolation
+	last_pos: Vector2<f32>,
+	pub path: Option<(usize, Path)>,
+	#[serde(default)]
+	pub cargo: Cargo,
+	//Trade route being sailed, if any
+	#[serde(default)]
+	pub itinerary: Option<Itinerary>,
+	//Ran into land, and won't move until given a new path
+	#[serde(default)]
+	pub aground: bool,
+	//Seconds left showing that the boat just traded
+	#[serde(skip)]
+	traded: f32,
+	//Boats around this one, refreshed by the environment before updating
+	#[serde(skip)]
+	pub neighbours: Vec<Neighbour>,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum BoatAction {
+	//Reached the end of its path
+	Arrived,
+}
+
+impl Boat {
+	const WAYPOINT_TOLERANCE: f32 = 150.;
+	const DESTINATION_TOLERANCE: f32 = 10.;
+
+	//Radius within which other boats are taken into account
+	pub const AWARENESS: f32 = 250.;
+	const SEPARATION_RADIUS: f32 = 80.;
+	const SEPARATION_WEIGHT: f32 = 2.;
+	const AVOID_RADIUS: f32 = 60.;
+	const AVOID_WEIGHT: f32 = 1.5;
+	//Seconds ahead to look for collisions
+	const LOOKAHEAD: f32 = 1.5;
+	//Boats slow down within this distance of their destination
+	const ARRIVAL_RADIUS: f32 = 150.;
+	const MIN_ARRIVAL_SPEED: f32 = 0.2;
+	//Fraction of full speed idle boats drift apart at when crowded
+	const IDLE_DRIFT: f32 = 0.25;
+
+	//Blocked boats that manage less than this fraction of their movement
+	//by sliding along the coast run aground.
+	const AGROUND_SLIDE: f32 = 0.25;
+	const AGROUND_TINT: GLvec4 = GLvec4(0.75, 0.6, 0.45, 1.);
+	const AGROUND_TILT: f32 = 20.;
+
+	//Crate shown on boats carrying cargo
+	const CARGO_SCALE: f32 = 0.5;
+	//Seconds the crate swells for after trading, and by how much
+	const TRADE_FLASH: f32 = 0.75;
+	const TRADE_SWELL: f32 = 0.5;
+
+	pub fn new(kind: BoatKind, pos: Vector2<f32>) -> Self {
+		Self {
+			kind,
+			pos,
+			dir: vec2(0., 1.),
+			last_pos: pos,
+			path: None,
+			cargo: Cargo::default(),
+			itinerary: None,
+			aground: false,
+			traded: 0.,
+			neighbours: vec![],
+		}
+	}
+
+	pub fn as_neighbour(&self) -> Neighbour {
+		let idle = self.path.is_none();
+
+		Neighbour {
+			pos: self.pos,
+			vel: if idle {
+				Vector2::zero()
+			} else {
+				self.kind.speed * self.dir
+			},
+			idle,
+		}
+	}
+
+	//Room left in the hold
+	pub fn space(&self) -> u32 {
+		self.kind.capacity.saturating_sub(self.cargo.total())
+	}
+
+	pub fn follow(&mut self, path: Path) {
+		self.path = Some((0, path));
+		self.aground = false;
+	}
+
+	//Follows a path from the node nearest the boat, so it keeps its place
+	//along a path that has been edited.
+	pub fn resume(&mut self, path: Path) {
+		let nearest = path
+			.nearest_node(self.pos, f32::INFINITY)
+			.unwrap_or_default();
+
+		self.path = Some((nearest, path));
+		self.aground = false;
+	}
+
+	//Start and end of the movement in the latest update
+	pub fn movement(&self) -> (Vector2<f32>, Vector2<f32>) {
+		(self.last_pos, self.pos)
+	}
+
+	//Applies the tile map's limits on the latest movement: `to` is where
+	//the boat could have gone given the water it sails through, and
+	//`reached` where it got to before hitting land. Bumping into a dock
+	//stops the boat without running it aground.
+	pub fn collide(&mut self, to: Vector2<f32>, reached: Vector2<f32>, dock: bool) {
+		let intended = to.distance(self.last_pos);
+		let achieved = reached.distance(self.last_pos);
+
+		if reached != to && !dock && achieved < Self::AGROUND_SLIDE * intended {
+			self.path = None;
+			self.aground = true;
+		}
+
+		self.pos = reached;
+	}
+
+	//Boats need their grid id to sign their dispatches, so the
+	//environment plans them instead of GameObject::plan.
+	pub fn plan_as(&self, id: GridId, messenger: &Sender<Dispatch<Signal>>) {
+		let signal = Signal::BoatNearby {
+			heading: self.dir.into(),
+		};
+
+		messenger
+			.send(Dispatch::local(self.pos.into(), signal).sent_by(id))
+			.expect("???");
+
+		if self.aground {
+			messenger
+				.send(Dispatch::local(self.pos.into(), Signal::Aground).sent_by(id))
+				.expect("???");
+		}
+	}
+}
+
+impl Boat {
+	//Dispatches sent to this boat in particular, which need its grid id
+	//like plan_as does.
+	pub fn receive_as(&mut self, id: GridId, messenger: &Messenger<Signal>) {
+		type SignalTy = <Signal as SignalType>::SignalKinds;
+
+		if messenger
+			.direct_receive(id, &[SignalTy::Traded])
+			.next()
+			.is_some()
+		{
+			self.traded = Self::TRADE_FLASH;
+		}
+	}
+}
+
+impl GameObject for Boat {
+	type Scene = World;
+	type Action = BoatAction;
+
+	fn update(
+		&mut self,
+		external: &External,
+		_messenger: &Messenger<Signal>,
+	) -> Option<Self::Action> {
+		self.last_pos = self.pos;
+		self.traded = (self.traded - external.delta).max(0.);
+
+		let separation = separation(self.pos, &self.neighbours, Self::SEPARATION_RADIUS);
+
+		let Some((wpi, path)) = self.path.as_ref() else {
+			if !self.aground {
+				self.pos += Self::IDLE_DRIFT * self.kind.speed * external.delta * separation;
+			}
+			return None;
+		};
+
+		let i = *wpi;
+		let n = path.nodes.len();
+
+		if i >= path.nodes.len() {
+			self.path.take();
+			self.pos += self.kind.speed * external.delta * self.dir;
+			return Some(BoatAction::Arrived);
+		}
+
+		let destination = path.nodes[i];
+		let desired_dir = destination - self.pos;
+
+		let distance = desired_dir.magnitude();
+
+		//Other boats nearer the destination may be in the way, so stop
+		//behind them rather than circling it.
+		let blocked = || {
+			self.neighbours.iter().any(|other| {
+				let ahead = other.pos.distance(destination) < distance;
+				let close = other.pos.distance(self.pos) < Self::SEPARATION_RADIUS;
+				ahead && close && (other.idle || distance < Self::ARRIVAL_RADIUS)
+			})
+		};
+
+		//Repeating paths have no destination to stop at
+		let last = i == n - 1 && !path.repeats();
+
+		//Less tolerance for last waypoint
+		let arrived = if last {
+			distance < Self::DESTINATION_TOLERANCE || blocked()
+		} else {
+			distance < Self::WAYPOINT_TOLERANCE
+		};
+
+		if arrived {
+			//Move on to next waypoint
+			let (wpi, path) = self.path.as_mut().unwrap();
+			*wpi += 1;
+
+			if *wpi == n {
+				match path.mode {
+					PathMode::Once => (),
+					PathMode::Loop => *wpi = 0,
+					PathMode::PingPong => {
+						path.nodes.reverse();
+						*wpi = 1;
+					}
+				}
+			}
+		}
+
+		let avoidance = avoidance(
+			self.pos,
+			self.kind.speed * self.dir,
+			&self.neighbours,
+			Self::AVOID_RADIUS,
+			Self::LOOKAHEAD,
+		);
+
+		let seek = if distance > f32::EPSILON {
+			desired_dir / distance
+		} else {
+			Vector2::zero()
+		};
+
+		let steering = seek + Self::SEPARATION_WEIGHT * separation + Self::AVOID_WEIGHT * avoidance;
+		let steering = if steering.magnitude2() > f32::EPSILON {
+			steering
+		} else {
+			self.dir
+		};
+
+		//gradual turning
+		let ang = angle(self.dir);
+		let Rad(diff) = steering.angle(self.dir);
+		let max_turn = self.kind.turn_rate() * external.delta;
+		let capped_diff = diff.signum() * diff.abs().min(max_turn);
+
+		let speed = if last {
+			let arrival = distance / Self::ARRIVAL_RADIUS;
+			self.kind.speed * arrival.clamp(Self::MIN_ARRIVAL_SPEED, 1.)
+		} else {
+			self.kind.speed
+		};
+
+		//Turning takes time, so also brake when heading into a boat
+		let brake = (1. + self.dir.dot(separation)).clamp(0., 1.);
+		let speed = speed * brake;
+
+		self.dir = unit_in_dir(ang + capped_diff);
+		self.pos += speed * external.delta * self.dir;
+
+		None
+	}
+
+	fn render(&self, win: &mut Window) {
+		if let Some((_, path)) = self.path.as_ref() {
+			path.render(win);
+		}
+
+		let pos = self.last_pos.lerp(self.pos, win.external().alpha);
+
+		let instance = Instance {
+			position: pos.into(),
+			rotation: GLfloat(angle(self.dir).to_degrees()),
+			..self.kind.instance(win)
+		};
+
+		if self.aground {
+			win.queue(Instance {
+				color_tint: Self::AGROUND_TINT,
+				rotation: GLfloat(instance.rotation.0 + Self::AGROUND_TILT),
+				..instance
+			});
+		} else {
+			win.queue(instance);
+		}
+
+		if !self.cargo.is_empty() || self.traded > 0. {
+			let swell = 1. + Self::TRADE_SWELL * self.traded / Self::TRADE_FLASH;
+			win.queue(Instance {
+				position: pos.into(),
+				..win
+					.external()
+					.instance(Texture::Wood)
+					.scale(Self::CARGO_SCALE * swell)
+			});
+		}
+	}
+}
+
+impl Griddable for Boat {
+	fn pos(&self) -> (f32, f32) {
+		self.pos.into()
+	}
+}
+
+impl GriddableMut for Boat {
+	fn set_pos(&mut self, pos: (f32, f32)) {
+		self.pos = pos.into();
+		self.last_pos = self.pos;
+	}
+}
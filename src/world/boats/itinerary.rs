@@ -0,0 +1,42 @@
+use super::*;
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+//What to do with a good at a stop. Amounts of None mean as much as
+//possible.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Order {
+	Load(Good, Option<u32>),
+	Unload(Good, Option<u32>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stop {
+	//Where boats moor at the stop's dock
+	pub dock: Vector2<f32>,
+	pub orders: Vec<Order>,
+}
+
+//Docks a boat trades between indefinitely, sailing back to the first
+//after the last.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Itinerary {
+	pub stops: Vec<Stop>,
+	//Stop currently being sailed to
+	pub next: usize,
+}
+
+impl Itinerary {
+	//None unless there are at least two stops to sail between
+	pub fn new(stops: Vec<Stop>) -> Option<Self> {
+		(stops.len() >= 2).then_some(Self { stops, next: 0 })
+	}
+
+	pub fn current(&self) -> &Stop {
+		&self.stops[self.next]
+	}
+
+	pub fn advance(&mut self) {
+		self.next = (self.next + 1) % self.stops.len();
+	}
+}
@@ -0,0 +1,51 @@
+use super::*;
+use crate::window::*;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+//Stats shared by every boat of a type, loaded from a data file so new
+//types don't need code changes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoatKind {
+	pub name: String,
+	pub speed: f32,
+	//Degrees per second
+	pub turn_speed: f32,
+	//Units of cargo it can carry
+	pub capacity: u32,
+	//Tiles deep enough for it to sail through
+	pub navigable: Vec<TileKind>,
+	pub sprite: Texture,
+	pub scale: f32,
+	pub tint: (f32, f32, f32, f32),
+}
+
+impl BoatKind {
+	pub const PATH: &'static str = "assets/boats.ron";
+
+	pub fn load_all(path: impl AsRef<std::path::Path>) -> io::Result<Vec<Self>> {
+		let file = std::fs::File::open(path)?;
+		let kinds: Vec<Self> = ron::de::from_reader(io::BufReader::new(file))
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		if kinds.is_empty() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"no boat kinds defined",
+			));
+		}
+
+		Ok(kinds)
+	}
+
+	pub fn turn_rate(&self) -> f32 {
+		self.turn_speed.to_radians()
+	}
+
+	pub fn instance(&self, win: &Window) -> Instance {
+		Instance {
+			color_tint: self.tint.into(),
+			..win.external().instance(self.sprite).scale(self.scale)
+		}
+	}
+}
@@ -1,7 +1,11 @@
+mod boat;
+mod itinerary;
+mod kind;
 mod steering;
-mod raft;
 
 use super::*;
 
+pub use boat::*;
+pub use itinerary::*;
+pub use kind::*;
 pub use steering::*;
-pub use raft::*;
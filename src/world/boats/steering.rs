@@ -1,10 +1,36 @@
 use super::*;
 use cgmath::*;
+use serde::{Deserialize, Serialize};
 
 pub type Waypoint = Vector2<f32>;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Path {
 	pub nodes: Vec<Waypoint>,
+	#[serde(default)]
+	pub mode: PathMode,
+}
+
+//What a boat does once it reaches the last node
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum PathMode {
+	#[default]
+	Once,
+	//Sails back to the first node and starts over
+	Loop,
+	//Sails the path backwards, then forwards again
+	PingPong,
+}
+
+impl PathMode {
+	pub fn next(self) -> Self {
+		use PathMode::*;
+		match self {
+			Once => Loop,
+			Loop => PingPong,
+			PingPong => Once,
+		}
+	}
 }
 
 impl Path {
@@ -13,7 +39,15 @@ impl Path {
 	pub const THICKNESS: f32 = 15.;
 
 	pub fn new(start: Waypoint) -> Self {
-		Self { nodes: vec![start] }
+		Self {
+			nodes: vec![start],
+			mode: PathMode::Once,
+		}
+	}
+
+	//Whether the path is sailed more than once
+	pub fn repeats(&self) -> bool {
+		self.mode != PathMode::Once && self.nodes.len() > 1
 	}
 
 	pub fn move_first(&mut self, waypoint: Waypoint) {
@@ -31,6 +65,164 @@ impl Path {
 	pub fn finish(&mut self) {
 		let _ = self.nodes.pop();
 	}
+
+	pub fn move_node(&mut self, i: usize, waypoint: Waypoint) {
+		self.nodes[i] = waypoint;
+	}
+
+	pub fn insert_node(&mut self, i: usize, waypoint: Waypoint) {
+		self.nodes.insert(i, waypoint);
+	}
+
+	//Paths always keep at least two nodes
+	pub fn remove_node(&mut self, i: usize) -> bool {
+		if self.nodes.len() > 2 {
+			self.nodes.remove(i);
+			true
+		} else {
+			false
+		}
+	}
+
+	pub fn nearest_node(&self, pos: Vector2<f32>, radius: f32) -> Option<usize> {
+		self.nodes
+			.iter()
+			.enumerate()
+			.map(|(i, node)| (i, node.distance(pos)))
+			.filter(|&(_, dist)| dist < radius)
+			.min_by(|(_, a), (_, b)| a.total_cmp(b))
+			.map(|(i, _)| i)
+	}
+
+	//Index a node inserted on the closest segment would have, and the
+	//closest point on that segment.
+	pub fn nearest_segment(&self, pos: Vector2<f32>, radius: f32) -> Option<(usize, Waypoint)> {
+		self.segments()
+			.map(|(i, a, b)| {
+				let ab = b - a;
+				let t = ((pos - a).dot(ab) / ab.magnitude2().max(f32::EPSILON)).clamp(0., 1.);
+				let closest = a + t * ab;
+				(i + 1, closest, closest.distance(pos))
+			})
+			.filter(|&(_, _, dist)| dist < radius)
+			.min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+			.map(|(i, closest, _)| (i, closest))
+	}
+
+	//Segments with the index of their first node, including the one
+	//closing a loop.
+	fn segments(&self) -> impl Iterator<Item = (usize, Waypoint, Waypoint)> + '_ {
+		let closing = match self.mode {
+			PathMode::Loop if self.nodes.len() > 2 => Some((
+				self.nodes.len() - 1,
+				*self.nodes.last().unwrap(),
+				self.nodes[0],
+			)),
+			_ => None,
+		};
+
+		self.nodes
+			.windows(2)
+			.enumerate()
+			.map(|(i, pair)| (i, pair[0], pair[1]))
+			.chain(closing)
+	}
+}
+
+impl Path {
+	//Copy of the path moved sideways and forwards relative to the
+	//direction each node is sailed in. The first node is left in place.
+	pub fn offset(&self, lateral: f32, forward: f32) -> Self {
+		let mut out = self.clone();
+
+		for i in 1..self.nodes.len() {
+			let heading = (self.nodes[i] - self.nodes[i - 1]).normalize();
+			if heading.x.is_nan() {
+				continue;
+			}
+
+			let side = vec2(-heading.y, heading.x);
+			out.nodes[i] += lateral * side + forward * heading;
+		}
+
+		out
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Formation {
+	//Abreast, perpendicular to the direction of travel
+	Line,
+	//Behind a leader, spreading out to both sides
+	Wedge,
+	//One behind another
+	#[default]
+	Column,
+}
+
+impl Formation {
+	pub const SPACING: f32 = 100.;
+
+	pub fn next(self) -> Self {
+		use Formation::*;
+		match self {
+			Line => Wedge,
+			Wedge => Column,
+			Column => Line,
+		}
+	}
+
+	//Sideways and forward offsets of a slot in a formation of n boats
+	pub fn slot(self, i: usize, n: usize) -> (f32, f32) {
+		use Formation::*;
+		match self {
+			Line => ((i as f32 - (n - 1) as f32 / 2.) * Self::SPACING, 0.),
+			Wedge => {
+				let rank = i.div_ceil(2) as f32;
+				let side = if i % 2 == 1 { -1. } else { 1. };
+				(side * rank * Self::SPACING, -rank * Self::SPACING)
+			}
+			Column => (0., -(i as f32) * Self::SPACING),
+		}
+	}
+
+	//Gives each boat its own copy of the path, starting from the boat and
+	//keeping to its slot. Boats take the slots closest to where they are
+	//relative to the first leg, so their paths don't cross at the start.
+	pub fn fan_out(self, path: &Path, boats: &[(GridId, Vector2<f32>)]) -> Vec<(GridId, Path)> {
+		let n = boats.len();
+		let Some(&first) = path.nodes.get(1) else {
+			return vec![];
+		};
+
+		let heading = (first - path.nodes[0]).normalize();
+		let side = vec2(-heading.y, heading.x);
+
+		let mut slots: Vec<_> = (0..n).map(|i| self.slot(i, n)).collect();
+		let mut boats = boats.to_vec();
+
+		//Sorting both boats and slots the same way pairs them up
+		let key = |(lateral, forward): (f32, f32)| match self {
+			Formation::Line => lateral,
+			_ => -forward,
+		};
+		slots.sort_by(|&a, &b| key(a).total_cmp(&key(b)));
+		boats.sort_by(|(_, a), (_, b)| {
+			let a = (a.dot(side), a.dot(heading));
+			let b = (b.dot(side), b.dot(heading));
+			key(a).total_cmp(&key(b))
+		});
+
+		boats
+			.into_iter()
+			.zip(slots)
+			.map(|((id, pos), (lateral, forward))| {
+				let mut path = path.offset(lateral, forward);
+				path.move_first(pos);
+				(id, path)
+			})
+			.collect()
+	}
 }
 
 impl GameObject for Path {
@@ -42,13 +234,11 @@ impl GameObject for Path {
 			return;
 		}
 
-		let mut start = self.nodes[0];
-		for &node in &self.nodes[1..] {
+		for (_, start, end) in self.segments() {
 			win.queue(Instance {
 				color_tint: Self::COLOR,
-				..win.external().line_instance(start, node, Self::THICKNESS)
+				..win.external().line_instance(start, end, Self::THICKNESS)
 			});
-			start = node;
 		}
 
 		let node_instance = win.external().instance(Texture::Node);
@@ -63,3 +253,198 @@ impl GameObject for Path {
 		}
 	}
 }
+
+//What a boat can see of another boat nearby
+#[derive(Clone, Copy)]
+pub struct Neighbour {
+	pub pos: Vector2<f32>,
+	pub vel: Vector2<f32>,
+	//Neighbours with no path are holding position
+	pub idle: bool,
+}
+
+//Push away from neighbours closer than radius, stronger the closer they are
+pub fn separation(pos: Vector2<f32>, neighbours: &[Neighbour], radius: f32) -> Vector2<f32> {
+	let mut push = Vector2::zero();
+
+	for other in neighbours {
+		let away = pos - other.pos;
+		let dist = away.magnitude();
+
+		if dist < radius {
+			//Boats in the exact same spot split in an arbitrary direction
+			let dir = if dist > f32::EPSILON {
+				away / dist
+			} else {
+				vec2(1., 0.)
+			};
+			push += dir * (1. - dist / radius);
+		}
+	}
+
+	push
+}
+
+//Steer sideways from neighbours that will come within radius in the next
+//`lookahead` seconds at the current velocities.
+pub fn avoidance(
+	pos: Vector2<f32>,
+	vel: Vector2<f32>,
+	neighbours: &[Neighbour],
+	radius: f32,
+	lookahead: f32,
+) -> Vector2<f32> {
+	let mut steer = Vector2::zero();
+
+	for other in neighbours {
+		let rel_pos = other.pos - pos;
+		let rel_vel = other.vel - vel;
+		let speed2 = rel_vel.magnitude2();
+
+		if speed2 < f32::EPSILON {
+			continue;
+		}
+
+		let t = (-rel_pos.dot(rel_vel) / speed2).clamp(0., lookahead);
+		let closest = rel_pos + t * rel_vel;
+		let miss = closest.magnitude();
+
+		if miss < radius && miss > f32::EPSILON {
+			steer -= closest / miss * (1. - t / lookahead);
+		}
+	}
+
+	steer
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::eng::FreeList;
+
+	const S: f32 = Formation::SPACING;
+
+	#[test]
+	fn formation_slots() {
+		let slots =
+			|formation: Formation, n| (0..n).map(|i| formation.slot(i, n)).collect::<Vec<_>>();
+
+		assert_eq!(
+			slots(Formation::Column, 3),
+			[(0., 0.), (0., -S), (0., -2. * S)]
+		);
+		assert_eq!(slots(Formation::Line, 3), [(-S, 0.), (0., 0.), (S, 0.)]);
+		assert_eq!(
+			slots(Formation::Wedge, 4),
+			[(0., 0.), (-S, -S), (S, -S), (-2. * S, -2. * S)]
+		);
+	}
+
+	#[test]
+	fn boats_keep_to_their_side() {
+		let mut ids = FreeList::new();
+		let boats: Vec<_> = [-200., 0., 200.]
+			.into_iter()
+			.map(|x| (ids.insert(()), vec2(x, 0.)))
+			.collect();
+
+		let mut path = Path::new(vec2(0., 0.));
+		path.add_waypoint(vec2(0., 1000.));
+
+		//Sailing north, so boats to the east take the slots to the east
+		for (id, path) in Formation::Line.fan_out(&path, &boats) {
+			let start = boats.iter().find(|(i, _)| *i == id).unwrap().1;
+			assert_eq!(path.nodes[0], start);
+			assert_eq!(path.nodes[1].x.signum(), start.x.signum());
+			assert_eq!(path.nodes[1].x.abs(), start.x.abs() / 2.);
+		}
+
+		//The boat furthest ahead leads the column
+		let boats = [(boats[0].0, vec2(0., 50.)), (boats[1].0, vec2(0., 300.))];
+		let fanned = Formation::Column.fan_out(&path, &boats);
+		let lead = fanned.iter().find(|(id, _)| *id == boats[1].0).unwrap();
+		assert_eq!(lead.1.nodes[1], vec2(0., 1000.));
+	}
+
+	#[test]
+	fn insert_on_closing_segment() {
+		let mut path = Path::new(vec2(0., 0.));
+		path.add_waypoint(vec2(100., 0.));
+		path.add_waypoint(vec2(100., 100.));
+
+		//Only loops have a segment back to the start
+		let pos = vec2(40., 60.);
+		assert_eq!(path.nearest_segment(pos, 20.), None);
+
+		path.mode = PathMode::Loop;
+		let (i, closest) = path.nearest_segment(pos, 20.).unwrap();
+		assert_eq!((i, closest), (3, vec2(50., 50.)));
+
+		path.insert_node(i, closest);
+		assert_eq!(path.nodes.last(), Some(&vec2(50., 50.)));
+	}
+
+	#[test]
+	fn paths_keep_two_nodes() {
+		let mut path = Path::new(vec2(0., 0.));
+		path.add_waypoint(vec2(100., 0.));
+		path.add_waypoint(vec2(100., 100.));
+
+		assert!(path.remove_node(1));
+		assert!(!path.remove_node(1));
+		assert!(!path.remove_node(0));
+		assert_eq!(path.nodes, [vec2(0., 0.), vec2(100., 100.)]);
+	}
+
+	fn neighbour(pos: Vector2<f32>, vel: Vector2<f32>) -> Neighbour {
+		Neighbour {
+			pos,
+			vel,
+			idle: false,
+		}
+	}
+
+	#[test]
+	fn separation_pushes_away() {
+		let still = Vector2::zero();
+		let origin = vec2(0., 0.);
+
+		let push = separation(origin, &[neighbour(vec2(20., 0.), still)], 80.);
+		assert_eq!(push, vec2(-0.75, 0.));
+
+		//Closer neighbours push harder, and those out of range not at all
+		let closer = separation(origin, &[neighbour(vec2(0., -40.), still)], 80.);
+		let further = separation(origin, &[neighbour(vec2(0., -60.), still)], 80.);
+		assert!(closer.y > further.y && further.y > 0.);
+		assert_eq!(
+			separation(origin, &[neighbour(vec2(90., 0.), still)], 80.),
+			still
+		);
+
+		//Boats on top of each other still split up
+		assert!(separation(origin, &[neighbour(origin, still)], 80.).magnitude() > 0.);
+	}
+
+	#[test]
+	fn avoidance_steers_from_collisions() {
+		let pos = vec2(0., 0.);
+		let vel = vec2(100., 0.);
+
+		//Head on, passing just north, so steer south
+		let oncoming = neighbour(vec2(100., 10.), vec2(-100., 0.));
+		let steer = avoidance(pos, vel, &[oncoming], 60., 1.5);
+		assert!(steer.y < 0. && steer.x.abs() < f32::EPSILON);
+
+		//Neither those sailing away nor those keeping pace need avoiding
+		let leaving = neighbour(vec2(-100., 10.), vec2(-100., 0.));
+		let alongside = neighbour(vec2(0., 30.), vel);
+		assert_eq!(
+			avoidance(pos, vel, &[leaving, alongside], 60., 1.5),
+			Vector2::zero()
+		);
+
+		//Nor those that will only come close beyond the lookahead
+		let distant = neighbour(vec2(1000., 10.), vec2(-100., 0.));
+		assert_eq!(avoidance(pos, vel, &[distant], 60., 1.5), Vector2::zero());
+	}
+}
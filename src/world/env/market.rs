@@ -0,0 +1,187 @@
+use super::*;
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+//Goods held at a dock, which produces one good and consumes another.
+//Prices rise as stock runs low and fall as it piles up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Market {
+	//Where boats moor to trade
+	pub pos: Vector2<f32>,
+	pub produces: Good,
+	pub demands: Good,
+	stock: [f32; <Good as strum::EnumCount>::COUNT],
+}
+
+impl Market {
+	//Units per second
+	const PRODUCTION: f32 = 0.2;
+	const CONSUMPTION: f32 = 0.15;
+	const MAX_STOCK: f32 = 100.;
+
+	//Stock at which goods sell for their base price
+	pub const TARGET_STOCK: f32 = 20.;
+	const MIN_PRICE_FACTOR: f32 = 0.25;
+	const MAX_PRICE_FACTOR: f32 = 4.;
+
+	//Deterministic in the world seed and where the dock is
+	pub fn generate(seed: u64, pos: Vector2<f32>) -> Self {
+		let goods: Vec<_> = Good::all().collect();
+
+		let mut rng = Rng::new(seed).stream(("market", NavGrid::tile_of(pos)));
+		let mut pick = |n: usize| ((rng.random() * n as f32) as usize).min(n - 1);
+
+		let produces = goods[pick(goods.len())];
+		let others: Vec<_> = goods.iter().filter(|&&good| good != produces).collect();
+		let demands = *others[pick(others.len())];
+
+		let mut stock = [Self::TARGET_STOCK; <Good as strum::EnumCount>::COUNT];
+		stock[produces as usize] = 2. * Self::TARGET_STOCK;
+		stock[demands as usize] = 0.;
+
+		Self {
+			pos,
+			produces,
+			demands,
+			stock,
+		}
+	}
+
+	pub fn update(&mut self, delta: f32) {
+		let produced = &mut self.stock[self.produces as usize];
+		*produced = (*produced + Self::PRODUCTION * delta).min(Self::MAX_STOCK);
+
+		let demanded = &mut self.stock[self.demands as usize];
+		*demanded = (*demanded - Self::CONSUMPTION * delta).max(0.);
+	}
+
+	pub fn stock(&self, good: Good) -> f32 {
+		self.stock[good as usize]
+	}
+
+	//Price of the next unit bought or sold
+	pub fn price(&self, good: Good) -> f32 {
+		let scarcity = Self::TARGET_STOCK / self.stock(good).max(1.);
+		good.base_price() * scarcity.clamp(Self::MIN_PRICE_FACTOR, Self::MAX_PRICE_FACTOR)
+	}
+
+	//Sells up to amount to the dock one unit at a time, so the price drops
+	//as it goes. Returns the units sold and what they fetched.
+	pub fn sell(&mut self, good: Good, amount: u32) -> (u32, f32) {
+		let mut revenue = 0.;
+		for sold in 0..amount {
+			if self.stock(good) + 1. > Self::MAX_STOCK {
+				return (sold, revenue);
+			}
+			revenue += self.price(good);
+			self.stock[good as usize] += 1.;
+		}
+		(amount, revenue)
+	}
+
+	//Buys up to amount from the dock without spending more than funds.
+	//Returns the units bought and what they cost.
+	pub fn buy(&mut self, good: Good, amount: u32, funds: f32) -> (u32, f32) {
+		let mut cost = 0.;
+		for bought in 0..amount {
+			let price = self.price(good);
+			if self.stock(good) < 1. || cost + price > funds {
+				return (bought, cost);
+			}
+			cost += price;
+			self.stock[good as usize] -= 1.;
+		}
+		(amount, cost)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn market() -> Market {
+		Market::generate(0, vec2(0., 0.))
+	}
+
+	#[test]
+	fn prices_follow_supply() {
+		let mut market = market();
+		let good = market.produces;
+		market.stock[good as usize] = Market::TARGET_STOCK;
+		assert_eq!(market.price(good), good.base_price());
+
+		//Every unit sold is worth less than the last, and buying them
+		//back costs more each time
+		let mut last = market.price(good);
+		for _ in 0..10 {
+			market.sell(good, 1);
+			assert!(market.price(good) < last);
+			last = market.price(good);
+		}
+		let (_, cheap) = market.buy(good, 1, f32::INFINITY);
+		market.stock[good as usize] = 5.;
+		let (_, dear) = market.buy(good, 1, f32::INFINITY);
+		assert!(dear > cheap);
+
+		//Prices only go so far either way
+		market.stock[good as usize] = 0.;
+		assert_eq!(
+			market.price(good),
+			good.base_price() * Market::MAX_PRICE_FACTOR
+		);
+		market.stock[good as usize] = Market::MAX_STOCK;
+		assert_eq!(
+			market.price(good),
+			good.base_price() * Market::MIN_PRICE_FACTOR
+		);
+	}
+
+	#[test]
+	fn stock_made_and_used_over_time() {
+		let mut market = market();
+		let (made, used) = (market.produces, market.demands);
+		market.stock[used as usize] = 1.;
+
+		let before = market.stock(made);
+		market.update(1.);
+		assert!(market.stock(made) > before);
+		assert!(market.stock(used) < 1.);
+
+		for _ in 0..10000 {
+			market.update(1.);
+		}
+		assert_eq!(market.stock(made), Market::MAX_STOCK);
+		assert_eq!(market.stock(used), 0.);
+	}
+
+	#[test]
+	fn trades_limited_by_stock_and_funds() {
+		let mut market = market();
+		let good = market.produces;
+
+		//Nothing is bought without the funds for the first unit
+		let price = market.price(good);
+		assert_eq!(market.buy(good, 5, price - 0.01), (0, 0.));
+		assert_eq!(market.buy(good, 0, f32::INFINITY), (0, 0.));
+
+		//Only as many as the funds cover
+		let stock = market.stock(good);
+		let (bought, cost) = market.buy(good, 10, 2.5 * price);
+		assert_eq!(bought, 2);
+		assert!(cost <= 2.5 * price);
+		assert_eq!(market.stock(good), stock - 2.);
+
+		//Nor more than the dock holds
+		let stock = market.stock(good);
+		let (bought, _) = market.buy(good, 1000, f32::INFINITY);
+		assert_eq!(bought as f32, stock);
+		assert_eq!(market.buy(good, 1, f32::INFINITY), (0, 0.));
+
+		//The dock only takes what it has room for
+		market.stock[good as usize] = Market::MAX_STOCK - 3.;
+		let (sold, revenue) = market.sell(good, 10);
+		assert_eq!(sold, 3);
+		assert!(revenue > 0.);
+		assert_eq!(market.sell(good, 1), (0, 0.));
+	}
+}
@@ -1,42 +1,316 @@
+mod market;
 mod puffin;
 mod tilemap;
 mod wave;
 
+use fnv::FnvHashMap;
 use puffin::*;
 use tilemap::*;
+
+pub use market::Market;
+pub use tilemap::{ChunkDelta, NavGrid, Preset, TileKind, TileMapSettings};
+
+#[cfg(feature = "hot-reload")]
+pub use tilemap::PresetWatch;
 use wave::*;
 
 use super::*;
 use crate::eng::*;
 use crate::window::*;
+use cgmath::*;
 
 pub struct Environment {
-	pub boats: Grid<Raft>,
+	pub boats: Grid<Boat>,
+	//Types of boat that can be placed, as loaded from BoatKind::PATH
+	pub kinds: Vec<BoatKind>,
 	pub tiles: TileMap,
+	//Markets at docks boats have visited, by the tile boats moor on
+	pub markets: FnvHashMap<Vector2<i32>, Market>,
+	//Player's money, spent buying cargo and earned selling it
+	pub treasury: f32,
 	waves: Vec<Wave>,
 	puffins: Vec<Puffin>,
+	routes: Vec<PendingRoute>,
+	trades: Vec<PendingTrade>,
+	//Decides where waves and puffins appear
+	spawner: Rng,
 }
 
+//Route being planned for a boat, with the updates left until it is
+//applied and how the boat should take it up
+type PendingRoute = (GridId, u32, Task<Path>, fn(&mut Boat, Path));
+
+//Trade route being set up for boats, with the updates left until it is
+//applied, the stops asked for and the docks found nearest them
+type PendingTrade = (
+	Vec<GridId>,
+	u32,
+	Vec<Vector2<f32>>,
+	Task<Vec<Option<Vector2<f32>>>>,
+);
+
 impl Environment {
 	const SMALL_RENDER_SCALE: f32 = 6000.;
+	//Routes and trade routes are applied a fixed number of updates after
+	//being requested, so replays stay deterministic: ROUTE_LATENCY, plus
+	//CHUNK_LATENCY for each chunk the planner might have to generate.
+	//That is long enough for it to do so on one core, so the main thread
+	//only waits on it on unusually slow machines.
+	const ROUTE_LATENCY: u32 = 3;
+	const CHUNK_LATENCY: f32 = 1.5;
+
+	pub const STARTING_TREASURY: f32 = 100.;
+	//How close to a dock a boat has to stop to trade there
+	const DOCK_RANGE: f32 = 2. * Tile::SIZE;
+	//How close to a dock a trade route stop has to be placed
+	const STOP_RANGE: f32 = 10. * Tile::SIZE;
+
+	//Generates the world from a preset in Preset::PATH
+	pub fn new(rng: &Rng, preset: &str) -> Self {
+		let settings = Preset::load(Preset::PATH, preset)
+			.expect("Unable to load world preset.")
+			.seeded(rng.stream("tiles").seed() as u32);
 
-	pub fn new() -> Self {
+		Self::with_settings(settings)
+	}
+
+	pub fn with_settings(settings: TileMapSettings) -> Self {
 		Self {
 			boats: Grid::new(256.),
-			tiles: TileMap::new(Default::default()),
+			kinds: BoatKind::load_all(BoatKind::PATH).expect("Unable to load boat kinds."),
+			tiles: TileMap::new(settings),
+			markets: Default::default(),
+			treasury: Self::STARTING_TREASURY,
 			waves: vec![],
 			puffins: vec![],
+			routes: vec![],
+			trades: vec![],
+			spawner: Rng::new(settings.seed as u64).stream("spawner"),
 		}
 	}
 
 	pub fn act(&mut self, action: UIAction) {
 		match action {
-			UIAction::Route(boat, path) => self.boats.get_mut(boat).unwrap().follow(path),
-			UIAction::Place(pos) => {
-				self.boats.insert(Raft::new(pos));
+			UIAction::Route(boats, path, formation) => {
+				self.cancel_itineraries(&boats);
+
+				let starts: Vec<_> = boats
+					.into_iter()
+					.filter_map(|id| Some((id, self.boats.get(id)?.pos)))
+					.collect();
+
+				for (boat, path) in formation.fan_out(&path, &starts) {
+					let navigable = &self.boats.get(boat).unwrap().kind.navigable;
+					let route = self.tiles.route(path, navigable);
+					self.await_route(boat, route, Boat::follow);
+				}
+			}
+			UIAction::Edit(boat, path, _) => {
+				self.cancel_itineraries(&[boat]);
+
+				let Some(navigable) = self.boats.get(boat).map(|boat| &boat.kind.navigable) else {
+					return;
+				};
+				let route = self.tiles.route(path, navigable);
+				self.await_route(boat, route, Boat::resume);
+			}
+			UIAction::Dock(boats) => {
+				for id in boats {
+					let Some(boat) = self.boats.get(id) else {
+						continue;
+					};
+
+					let route = self.tiles.route_to_dock(boat.pos, &boat.kind.navigable);
+					self.await_route(id, route, Self::moor);
+				}
+			}
+			UIAction::Trade(boats, path) => {
+				let docks = self.tiles.nearest_docks(path.nodes.clone());
+				let wait = Self::latency(docks.chunks);
+				self.trades.push((boats, wait, path.nodes, docks.task));
+			}
+			UIAction::Build(pos) => {
+				let Some(pier) = self.tiles.plan_pier(pos) else {
+					return;
+				};
+
+				//Piers can't be built over boats
+				let blocked = pier
+					.tiles
+					.iter()
+					.any(|&tile| self.boats.query_at(tile, Tile::SIZE).next().is_some());
+				if !blocked {
+					self.tiles.build_pier(pier);
+				}
+			}
+			UIAction::Place(pos, kind) => {
+				self.boats.insert(Boat::new(self.kinds[kind].clone(), pos));
+			}
+		}
+	}
+}
+
+impl Environment {
+	//Key in markets of the dock within range of pos, setting up its
+	//market on the first visit
+	pub fn dock_near(&mut self, pos: Vector2<f32>) -> Option<Vector2<i32>> {
+		let dock = self.tiles.dock_within(pos, Self::DOCK_RANGE)?;
+		Some(self.market_at(dock))
+	}
+
+	//Key in markets of the dock boats moor at, setting up its market on
+	//the first visit
+	fn market_at(&mut self, dock: Vector2<f32>) -> Vector2<i32> {
+		let seed = self.tiles.settings().seed as u64;
+		let key = NavGrid::tile_of(dock);
+		self.markets
+			.entry(key)
+			.or_insert_with(|| Market::generate(seed, dock));
+
+		key
+	}
+
+	//Sends boats round the docks nearest each stop asked for, skipping
+	//stops too far from any dock
+	fn start_trade(
+		&mut self,
+		boats: Vec<GridId>,
+		nodes: &[Vector2<f32>],
+		nearest: &[Option<Vector2<f32>>],
+	) {
+		let mut docks: Vec<(Vector2<f32>, Good, Good)> = vec![];
+		for (&node, &dock) in nodes.iter().zip(nearest) {
+			let Some(dock) = dock else {
+				continue;
+			};
+			if dock.distance(node) > Self::STOP_RANGE
+				|| docks.last().is_some_and(|&(last, ..)| last == dock)
+			{
+				continue;
+			}
+
+			let key = self.market_at(dock);
+			let market = &self.markets[&key];
+			docks.push((dock, market.produces, market.demands));
+		}
+
+		//Each stop unloads what its dock wants, and loads what it makes if
+		//another stop wants it
+		let stops = docks
+			.iter()
+			.map(|&(dock, produces, demands)| {
+				let mut orders = vec![Order::Unload(demands, None)];
+				if docks.iter().any(|&(.., wanted)| wanted == produces) {
+					orders.push(Order::Load(produces, None));
+				}
+				Stop { dock, orders }
+			})
+			.collect();
+
+		let Some(itinerary) = Itinerary::new(stops) else {
+			return;
+		};
+
+		for id in boats {
+			if let Some(boat) = self.boats.get_mut(id) {
+				boat.itinerary = Some(itinerary.clone());
+				self.sail_to_stop(id);
+			}
+		}
+	}
+
+	//Follows a route to a dock. Boats without a dock in reach carry on as
+	//they were.
+	fn moor(boat: &mut Boat, route: Path) {
+		if route.nodes.len() > 1 {
+			boat.follow(route);
+		}
+	}
+
+	//Sets off for the next stop on a boat's itinerary
+	fn sail_to_stop(&mut self, id: GridId) {
+		let Some(boat) = self.boats.get(id) else {
+			return;
+		};
+		let Some(itinerary) = boat.itinerary.as_ref() else {
+			return;
+		};
+
+		let mut path = Path::new(boat.pos);
+		path.add_waypoint(itinerary.current().dock);
+
+		let route = self.tiles.route(path, &boat.kind.navigable);
+		self.await_route(id, route, Boat::follow);
+	}
+
+	fn await_route(&mut self, id: GridId, route: Planned<Path>, take_up: fn(&mut Boat, Path)) {
+		let wait = Self::latency(route.chunks);
+		self.routes.push((id, wait, route.task, take_up));
+	}
+
+	//Updates to wait for something planned on another thread
+	fn latency(chunks: usize) -> u32 {
+		Self::ROUTE_LATENCY + (chunks as f32 * Self::CHUNK_LATENCY).ceil() as u32
+	}
+
+	//Boats given orders by hand stop following their trade routes
+	fn cancel_itineraries(&mut self, boats: &[GridId]) {
+		for &id in boats {
+			if let Some(boat) = self.boats.get_mut(id) {
+				boat.itinerary = None;
 			}
 		}
 	}
+
+	//Carries out orders at the dock a boat is moored at, as far as its
+	//hold and the treasury allow
+	pub fn trade(&mut self, id: GridId, orders: &[Order], messenger: &Sender<Dispatch<Signal>>) {
+		let Some(pos) = self.boats.get(id).map(|boat| boat.pos) else {
+			return;
+		};
+		let Some(dock) = self.dock_near(pos) else {
+			return;
+		};
+		let mut treasury = self.treasury;
+		let market = self.markets.get_mut(&dock).unwrap();
+		let boat = self.boats.get_mut(id).unwrap();
+
+		let mut traded = vec![];
+
+		for &order in orders {
+			match order {
+				Order::Unload(good, amount) => {
+					let amount = amount.unwrap_or(u32::MAX).min(boat.cargo.get(good));
+					let (sold, revenue) = market.sell(good, amount);
+					if sold > 0 {
+						boat.cargo.take(good, sold);
+						treasury += revenue;
+						traded.push((good, -(sold as i32), revenue));
+					}
+				}
+				Order::Load(good, amount) => {
+					let amount = amount.unwrap_or(u32::MAX).min(boat.space());
+					let (bought, cost) = market.buy(good, amount, treasury);
+					if bought > 0 {
+						boat.cargo.add(good, bought);
+						treasury -= cost;
+						traded.push((good, bought as i32, cost));
+					}
+				}
+			}
+		}
+
+		self.treasury = treasury;
+
+		for (good, amount, value) in traded {
+			let signal = Signal::Traded {
+				good,
+				amount,
+				value,
+			};
+			messenger.send(Dispatch::direct(id, signal)).expect("???");
+		}
+	}
 }
 
 impl GameObject for Environment {
@@ -44,7 +318,9 @@ impl GameObject for Environment {
 	type Action = ();
 
 	fn plan(&self, world: &World, external: &External, messenger: &Sender<Dispatch<Signal>>) {
-		self.boats.plan(world, external, messenger);
+		for (id, boat) in self.boats.iter_with_ids() {
+			boat.plan_as(id, messenger);
+		}
 		for puffin in &self.puffins {
 			puffin.plan(world, external, messenger);
 		}
@@ -56,14 +332,100 @@ impl GameObject for Environment {
 		messenger: &Messenger<Signal>,
 	) -> Option<Self::Action> {
 		self.tiles.update(external, messenger);
-		self.boats.update(external, messenger);
+
+		for (boats, wait, nodes, docks) in std::mem::take(&mut self.trades) {
+			if wait > 0 {
+				self.trades.push((boats, wait - 1, nodes, docks));
+			} else {
+				self.start_trade(boats, &nodes, docks.get());
+			}
+		}
+
+		let boats = &mut self.boats;
+		self.routes.retain_mut(|(boat, wait, route, take_up)| {
+			if *wait > 0 {
+				*wait -= 1;
+				return true;
+			}
+
+			if let Some(boat) = boats.get_mut(*boat) {
+				take_up(boat, route.get().clone());
+			}
+			false
+		});
+
+		//Boats only sail within their awareness each step
+		let positions = self.boats.iter().map(|boat| boat.pos);
+		self.tiles.anchor(positions, Boat::AWARENESS);
+
+		let neighbours: Vec<_> = self
+			.boats
+			.iter_with_ids()
+			.map(|(id, boat)| {
+				let near = self
+					.boats
+					.query_at(boat.pos, Boat::AWARENESS)
+					.filter(|&(other, _)| other != id)
+					.map(|(_, other)| other.as_neighbour())
+					.collect();
+				(id, near)
+			})
+			.collect();
+
+		for (id, near) in neighbours {
+			self.boats.get_mut(id).unwrap().neighbours = near;
+		}
+
+		let mut arrived = vec![];
+		for (id, boat) in self.boats.iter_mut_with_ids() {
+			boat.receive_as(id, messenger);
+			if boat.update(external, messenger) == Some(BoatAction::Arrived) {
+				arrived.push(id);
+			}
+
+			//Shallow water slows boats down, and water too shallow for
+			//them stops them
+			let (from, to) = boat.movement();
+			let to = from + (to - from) / self.tiles.travel_cost(from);
+			let reached = self.tiles.sweep(from, to, &boat.kind.navigable);
+			let dock = self
+				.tiles
+				.generated_tile_f(to)
+				.is_some_and(|tile| tile.kind == TileKind::Dock);
+			boat.collide(to, reached, dock);
+		}
+		self.boats.maintain();
+
+		let sender = messenger.sender();
+		for id in arrived {
+			let boat = self.boats.get(id).unwrap();
+			let pos = boat.pos;
+			let Some(stop) = boat.itinerary.as_ref().map(|it| it.current().clone()) else {
+				continue;
+			};
+
+			//Boats that didn't make it to the stop's dock carry on to the
+			//next without trading
+			if self.dock_near(pos) == Some(NavGrid::tile_of(stop.dock)) {
+				self.trade(id, &stop.orders, &sender);
+			}
+
+			if let Some(itinerary) = self.boats.get_mut(id).unwrap().itinerary.as_mut() {
+				itinerary.advance();
+				self.sail_to_stop(id);
+			}
+		}
+
+		for market in self.markets.values_mut() {
+			market.update(external.delta);
+		}
 
 		if external.camera.scale < Self::SMALL_RENDER_SCALE {
-			if let Some(wave) = Wave::maybe_spawn(&mut self.tiles, external) {
+			if let Some(wave) = Wave::maybe_spawn(&mut self.tiles, external, &mut self.spawner) {
 				self.waves.push(wave)
 			}
 
-			if let Some(puffin) = Puffin::maybe_spawn(external) {
+			if let Some(puffin) = Puffin::maybe_spawn(external, &mut self.spawner) {
 				self.puffins.push(puffin)
 			}
 		}
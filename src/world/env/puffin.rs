@@ -9,7 +9,17 @@ pub struct Puffin {
 	heading: Vector2<i32>,
 	flipped: bool,
 	animation: Animation<Texture>,
-	scared_of: Option<Vector2<f32>>,
+	scared_of: Option<Scare>,
+	//Raft the puffin is currently flying away from
+	fleeing: Option<GridId>,
+	rng: Rng,
+}
+
+#[derive(Clone, Copy)]
+struct Scare {
+	pos: Vector2<f32>,
+	heading: Vector2<f32>,
+	by: Option<GridId>,
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -29,13 +39,13 @@ impl Puffin {
 	const SCARE_DIST: f32 = 5. * Tile::SIZE;
 	const SPAWN_MARGIN: f32 = 1.25;
 
-	pub fn maybe_spawn(external: &External) -> Option<Self> {
+	pub fn maybe_spawn(external: &External, rng: &mut Rng) -> Option<Self> {
 		let v = external.view_dims() / 2.;
 
-		if probability(Self::DENSITY * external.delta * v.x * v.y) {
+		if rng.probability(Self::DENSITY * external.delta * v.x * v.y) {
 			let pos = external.camera.pos;
 
-			let offset = v.map(|f| rand_in(-f, f)) * Self::SPAWN_MARGIN;
+			let offset = v.map(|f| rng.rand_in(-f, f)) * Self::SPAWN_MARGIN;
 			let heading = snap_to_grid(pos + offset, Self::SPOT_DIMS);
 
 			let signum = offset.map(f32::signum);
@@ -50,12 +60,15 @@ impl Puffin {
 				heading,
 				flipped: source.x < heading.x,
 				animation: Animation::new(
+					external.now,
 					Texture::PuffinFlap,
 					1.,
 					curves::SIN_BOUNCE,
 					f32::INFINITY,
 				),
 				scared_of: None,
+				fleeing: None,
+				rng: rng.split(),
 			})
 		} else {
 			None
@@ -94,12 +107,21 @@ impl Automaton for Puffin {
 		type SignalTy = <Signal as SignalType>::SignalKinds;
 
 		let destination = self.heading.map(|f| f as f32);
-		for message in
+		for dispatch in
 			messenger.local_receive(destination, Self::SCARE_DIST, &[SignalTy::BoatNearby])
 		{
-			match message {
-				(pos, BoatNearby) => {
-					self.scared_of = Some(pos.into());
+			//Already on the way out, but another raft can still give chase
+			if dispatch.sender().is_some() && dispatch.sender() == self.fleeing {
+				continue;
+			}
+
+			match *dispatch.signal() {
+				BoatNearby { heading } => {
+					self.scared_of = Some(Scare {
+						pos: dispatch.pos().unwrap().into(),
+						heading: heading.into(),
+						by: dispatch.sender(),
+					});
 				}
 				_ => unreachable!(),
 			}
@@ -114,7 +136,7 @@ impl Automaton for Puffin {
 		}
 	}
 
-	fn next_state(&self, external: &External) -> Self::State {
+	fn next_state(&mut self, external: &External) -> Self::State {
 		let at_destination = self
 			.position(external)
 			.distance2(self.heading.map(|f| f as f32))
@@ -132,35 +154,42 @@ impl Automaton for Puffin {
 					PuffinFlap
 				}
 			} else {
-				self.by_probability(&[
-					(Puffin, 0.90),
-					(PuffinFlip, 0.045),
-					(PuffinPeck, 0.045),
-					(PuffinFly, 0.01),
-				])
+				let roll = self.rng.random();
+				self.by_probability(
+					roll,
+					&[
+						(Puffin, 0.90),
+						(PuffinFlip, 0.045),
+						(PuffinPeck, 0.045),
+						(PuffinFly, 0.01),
+					],
+				)
 			}
 		} else {
 			self.state()
 		}
 	}
 
-	fn exit_to(&mut self, _new: Self::State) {
+	fn exit_to(&mut self, _new: Self::State, _external: &External) {
 		match self.state() {
 			PuffinFlap | PuffinFly => (),
 			_ => {
-				if probability(0.05) {
+				if self.rng.probability(0.05) {
 					self.flipped = !self.flipped
 				}
 			}
 		}
 	}
 
-	fn enter_from(&mut self, old: Self::State) {
+	fn enter_from(&mut self, old: Self::State, external: &External) {
 		use Texture::*;
 		let mut reps = 1.;
 		let (duration, curve) = match self.state() {
-			Puffin => (rand_in(1., 6.), curves::FIRST),
-			PuffinFlip => (rand_in(1., 6.), curves::FIRST),
+			Puffin => {
+				self.fleeing = None;
+				(self.rng.rand_in(1., 6.), curves::FIRST)
+			}
+			PuffinFlip => (self.rng.rand_in(1., 6.), curves::FIRST),
 			PuffinPeck => (0.65, curves::LINEAR),
 			PuffinFly if old == PuffinFlap => {
 				self.source = self.heading;
@@ -168,12 +197,18 @@ impl Automaton for Puffin {
 			}
 			PuffinFly => {
 				//Find new home
-				match self.scared_of {
-					Some(pos) => {
+				match self.scared_of.take() {
+					Some(scare) => {
 						let current = self.source.map(|f| f as f32);
-						let dir = (current - pos).normalize_to(Self::FLEE_DIST);
+						let away = (current - scare.pos).normalize();
+
+						//Veer out of the raft's way rather than ahead of it
+						let side = vec2(-scare.heading.y, scare.heading.x);
+						let side = side * away.dot(side).signum();
+
+						let dir = (away + side).normalize_to(Self::FLEE_DIST);
 						self.heading = snap_to_grid(current + dir, Self::SPOT_DIMS);
-						self.scared_of = None;
+						self.fleeing = scare.by;
 					}
 					None =>
 					//Different x values to avoid unrealistic movement.
@@ -182,7 +217,7 @@ impl Automaton for Puffin {
 							let fsource = self.source.map(|f| f as f32);
 
 							self.heading = snap_to_grid(
-								fsource + rand_in2d(-Self::FLEE_DIST, Self::FLEE_DIST),
+								fsource + self.rng.rand_in2d(-Self::FLEE_DIST, Self::FLEE_DIST),
 								Self::SPOT_DIMS,
 							);
 						}
@@ -200,7 +235,7 @@ impl Automaton for Puffin {
 			_ => unreachable!(),
 		};
 
-		self.animation = Animation::new(self.state(), duration, curve, reps);
+		self.animation = Animation::new(external.now, self.state(), duration, curve, reps);
 	}
 
 	fn state(&self) -> Self::State {
@@ -0,0 +1,110 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+//Climate of a region, from the temperature and moisture noise. Decides
+//the colours of its tiles and what grows there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Biome {
+	Temperate,
+	Tropical,
+	Desert,
+	Arctic,
+}
+
+impl Biome {
+	pub fn of(settings: &TileMapSettings, reading: &Reading) -> Self {
+		use Biome::*;
+		if reading.temperature < settings.cold_lvl {
+			Arctic
+		} else if reading.temperature < settings.hot_lvl {
+			Temperate
+		} else if reading.moisture < settings.dry_lvl {
+			Desert
+		} else {
+			Tropical
+		}
+	}
+
+	pub fn color(&self, settings: &TileMapSettings, kind: TileKind) -> (f32, f32, f32, f32) {
+		use Biome::*;
+		use TileKind::*;
+
+		let palettes = &settings.palettes;
+		let palette = match self {
+			Temperate => &palettes.temperate,
+			Tropical => &palettes.tropical,
+			Desert => &palettes.desert,
+			Arctic => &palettes.arctic,
+		};
+
+		let color = match kind {
+			Land => palette.land,
+			Shore => palette.shore,
+			Sea => palette.sea,
+			DeepSea => palette.deep_sea,
+			Wood => palette.wood,
+			Reef => palette.reef,
+			Dock => None,
+		};
+
+		color.unwrap_or_else(|| kind.color())
+	}
+
+	pub fn has_forests(&self) -> bool {
+		!matches!(self, Biome::Desert)
+	}
+
+	//Reefs only grow in warm water
+	pub fn has_reefs(&self) -> bool {
+		matches!(self, Biome::Tropical)
+	}
+}
+
+//Colours of tile kinds in a biome, in place of those from TileKind::color
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+	pub land: Option<(f32, f32, f32, f32)>,
+	pub shore: Option<(f32, f32, f32, f32)>,
+	pub sea: Option<(f32, f32, f32, f32)>,
+	pub deep_sea: Option<(f32, f32, f32, f32)>,
+	pub wood: Option<(f32, f32, f32, f32)>,
+	pub reef: Option<(f32, f32, f32, f32)>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palettes {
+	pub temperate: Palette,
+	pub tropical: Palette,
+	pub desert: Palette,
+	pub arctic: Palette,
+}
+
+impl Default for Palettes {
+	fn default() -> Self {
+		Self {
+			temperate: Palette::default(),
+			tropical: Palette {
+				land: Some((40., 150., 60., 255.)),
+				shore: Some((245., 235., 190., 255.)),
+				sea: Some((40., 170., 190., 255.)),
+				wood: Some((20., 110., 40., 255.)),
+				..Default::default()
+			},
+			desert: Palette {
+				land: Some((200., 160., 90., 255.)),
+				shore: Some((235., 205., 140., 255.)),
+				..Default::default()
+			},
+			arctic: Palette {
+				land: Some((225., 230., 235., 255.)),
+				shore: Some((170., 175., 180., 255.)),
+				sea: Some((70., 105., 140., 255.)),
+				deep_sea: Some((20., 40., 60., 255.)),
+				wood: Some((30., 70., 55., 255.)),
+				..Default::default()
+			},
+		}
+	}
+}
@@ -3,9 +3,18 @@ use std::cell::Cell;
 use super::*;
 use cgmath::*;
 
+//Pier to build from the shore, with the centers of its tiles and where
+//boats moor at the end of it
+pub struct Pier {
+	pub tiles: Vec<Vector2<f32>>,
+	pub berth: Vector2<f32>,
+}
+
 pub struct Chunk {
 	pub cell_pos: Vector2<i32>,
 	tiles: Box<[Tile; Self::DIMENSION * Self::DIMENSION]>,
+	//Where boats moor at each dock in the chunk, in world coordinates
+	pub docks: Vec<Vector2<f32>>,
 	cache: Cell<Option<CacheId>>,
 }
 
@@ -16,6 +25,12 @@ impl Chunk {
 	//Size of a chunk, in pixels
 	pub const WIDTH: f32 = Self::DIMENSION as f32 * Tile::SIZE;
 
+	//Drawn in place of chunks still being generated
+	const PLACEHOLDER_TINT: GLvec4 = GLvec4(0.2, 0.35, 0.5, 1.);
+
+	//Longest pier, in tiles, built to reach water deep enough for a dock
+	const MAX_PIER: usize = 12;
+
 	pub fn get_tile(&self, i: usize, j: usize) -> &Tile {
 		&self.tiles[i * Self::DIMENSION + j]
 	}
@@ -24,6 +39,11 @@ impl Chunk {
 		&mut self.tiles[i * Self::DIMENSION + j]
 	}
 
+	//Kind of every tile, in the same order as the tiles
+	pub fn kinds(&self) -> Vec<TileKind> {
+		self.tiles.iter().map(|tile| tile.kind).collect()
+	}
+
 	pub fn chunk_id(v: Vector2<f32>) -> Vector2<i32> {
 		v.map(|d| d.div_euclid(Chunk::WIDTH) as i32)
 	}
@@ -50,25 +70,123 @@ impl Chunk {
 
 				let pos = (cell + offset) / settings.scale;
 
-				let reading = noise.read(pos.into());
+				let reading = noise.read(&settings, pos.into());
 
-				tiles.push(Tile::generate_geography(&settings, reading));
+				tiles.push(Tile::generate_geography(&settings, &reading));
 			}
 		}
 
 		let boxed_tiles = tiles.into_boxed_slice();
 		let tiles = boxed_tiles.try_into().unwrap();
 
-		//Attempt to place dock in this chunk
-		if probability(settings.dock_prob) {
-			//TODO!
-		}
-
-		Self {
+		let mut chunk = Self {
 			cell_pos,
 			tiles,
+			docks: vec![],
 			cache: None.into(),
+		};
+
+		let mut rng = Rng::new(settings.seed as u64).stream(cell_pos);
+
+		//Attempt to place dock in this chunk
+		if rng.probability(settings.dock_prob) {
+			let count = Self::DIMENSION * Self::DIMENSION;
+			let first = (rng.random() * count as f32) as usize;
+
+			//Scan from a random tile for the first shore that a pier
+			//can be built out from
+			for k in 0..count {
+				let index = (first + k) % count;
+				let (i, j) = (index / Self::DIMENSION, index % Self::DIMENSION);
+
+				if let Some(pier) = chunk.plan_pier(&settings, i, j) {
+					for &tile in &pier.tiles {
+						let [i, j] = Self::tile_id(tile).1.into();
+						chunk.get_tile_mut(i, j).build(TileKind::Dock);
+					}
+					chunk.docks.push(pier.berth);
+					break;
+				}
+			}
+		}
+
+		chunk
+	}
+
+	pub fn placeholder(cell_pos: Vector2<i32>, external: &External) -> Instance {
+		let center = (cell_pos.map(|f| f as f32) + vec2(0.5, 0.5)) * Self::WIDTH;
+
+		Instance {
+			position: center.into(),
+			color_tint: Self::PLACEHOLDER_TINT,
+			..external.instance(Texture::Flat)
+		}
+		.scale(Self::WIDTH)
+	}
+
+	pub fn apply(&mut self, delta: &ChunkDelta) {
+		for &((i, j), tile) in &delta.tiles {
+			tile.apply(self.get_tile_mut(i, j));
+		}
+		for &dock in &delta.docks {
+			if !self.docks.contains(&dock) {
+				self.docks.push(dock);
+			}
+		}
+		self.invalidate();
+	}
+
+	//Rebuild the cached instances on the next render
+	pub fn invalidate(&self) {
+		self.cache.take();
+	}
+
+	//Plans a pier from the shore tile at (i, j) straight out to sea, until
+	//the water is at least as deep as settings.dock_depth, if there is
+	//room for it.
+	pub fn plan_pier(&self, settings: &TileMapSettings, i: usize, j: usize) -> Option<Pier> {
+		let is_water = |tile: &Tile| matches!(tile.kind, TileKind::Sea | TileKind::DeepSea);
+
+		if self.get_tile(i, j).kind != TileKind::Shore {
+			return None;
+		}
+
+		let in_chunk = |i: isize, j: isize| {
+			let range = 0..Self::DIMENSION as isize;
+			(range.contains(&i) && range.contains(&j)).then_some((i as usize, j as usize))
+		};
+
+		let (di, dj) = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+			.into_iter()
+			.find(|&(di, dj)| {
+				in_chunk(i as isize + di, j as isize + dj)
+					.is_some_and(|(i, j)| is_water(self.get_tile(i, j)))
+			})?;
+
+		let mut pier = vec![(i, j)];
+		for step in 1..=Self::MAX_PIER as isize {
+			let (ti, tj) = in_chunk(i as isize + step * di, j as isize + step * dj)?;
+			let tile = self.get_tile(ti, tj);
+
+			if !is_water(tile) {
+				return None;
+			}
+
+			if tile.height <= settings.dock_depth {
+				let cell = self.cell_pos.map(|f| f as f32) * Self::WIDTH;
+				let center =
+					|i: usize, j: usize| cell + vec2(i as f32 + 0.5, j as f32 + 0.5) * Tile::SIZE;
+
+				return Some(Pier {
+					tiles: pier.into_iter().map(|(i, j)| center(i, j)).collect(),
+					berth: center(ti, tj),
+				});
+			}
+
+			pier.push((ti, tj));
 		}
+
+		None
 	}
 }
 
@@ -104,6 +222,6 @@ impl GameObject for Chunk {
 	}
 
 	fn cleanup(&mut self) {
-		self.cache.take();
+		self.invalidate();
 	}
 }
@@ -0,0 +1,48 @@
+use super::*;
+use crate::window::GLvec4;
+use cgmath::*;
+use serde::{Deserialize, Serialize};
+
+//A tile as changed since generation
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TileDelta {
+	pub kind: TileKind,
+	pub height: f32,
+	pub tint: (f32, f32, f32, f32),
+}
+
+impl TileDelta {
+	pub fn of(tile: &Tile) -> Self {
+		let GLvec4(r, g, b, a) = tile.tint;
+		Self {
+			kind: tile.kind,
+			height: tile.height,
+			tint: (r, g, b, a),
+		}
+	}
+
+	pub fn apply(&self, tile: &mut Tile) {
+		tile.kind = self.kind;
+		tile.height = self.height;
+		tile.tint = self.tint.into();
+	}
+}
+
+//Every edited tile in a chunk, by its index within the chunk, and where
+//boats moor at docks built there. Applied on top of Chunk::generate, so
+//edits survive the chunk being regenerated.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ChunkDelta {
+	pub tiles: Vec<((usize, usize), TileDelta)>,
+	#[serde(default)]
+	pub docks: Vec<Vector2<f32>>,
+}
+
+impl ChunkDelta {
+	pub fn set(&mut self, i: usize, j: usize, delta: TileDelta) {
+		match self.tiles.iter_mut().find(|(index, _)| *index == (i, j)) {
+			Some((_, old)) => *old = delta,
+			None => self.tiles.push(((i, j), delta)),
+		}
+	}
+}
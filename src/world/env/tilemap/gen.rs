@@ -1,40 +1,106 @@
+use super::*;
 use noise::*;
 
 type WaterGen = BasicMulti<OpenSimplex>;
 type IslandGen = BasicMulti<OpenSimplex>;
+type ClimateGen = OpenSimplex;
 
 #[derive(Clone)]
 pub struct Generator {
 	water_gen: WaterGen,
 	island_gen: IslandGen,
+	//Where archipelagos rise out of the ocean
+	archipelago_gen: ClimateGen,
+	temperature_gen: ClimateGen,
+	moisture_gen: ClimateGen,
+	reef_gen: ClimateGen,
+}
+
+//Everything generated about a point on the map
+pub struct Reading {
+	pub height: f32,
+	pub temperature: f32,
+	pub moisture: f32,
+	pub reef: f32,
 }
 
 impl Generator {
-	const TAPER_SCALE: f32 = 2.5;
 	const TAPER_POW: f32 = 3.;
 	const WAVE_AMPLITUDE: f32 = 0.3;
 	const WAVE_BIAS: f32 = 0.25;
 
 	pub fn init(seed: u32) -> Self {
-		let water_gen = WaterGen::default().set_seed(seed);
-		let island_gen = IslandGen::default().set_seed(seed + 1);
+		//Fractal noise seeds one source per octave, counting up from its
+		//own seed without wrapping, so its seed wraps early enough to leave
+		//room for that below u32::MAX
+		let limit = u32::MAX - WaterGen::MAX_OCTAVES as u32;
+		let fractal_seed = |offset: u32| seed.wrapping_add(offset) % limit;
 
 		Self {
-			water_gen,
-			island_gen,
+			water_gen: WaterGen::default().set_seed(fractal_seed(0)),
+			island_gen: IslandGen::default().set_seed(fractal_seed(1)),
+			archipelago_gen: ClimateGen::new(seed.wrapping_add(2)),
+			temperature_gen: ClimateGen::new(seed.wrapping_add(3)),
+			moisture_gen: ClimateGen::new(seed.wrapping_add(4)),
+			reef_gen: ClimateGen::new(seed.wrapping_add(5)),
 		}
 	}
 
-	pub fn read(&self, pos: [f32; 2]) -> f32 {
+	//pos is in units of settings.scale
+	pub fn read(&self, settings: &TileMapSettings, pos: [f32; 2]) -> Reading {
 		let [x, y] = pos;
+		let point = [x as f64, y as f64];
+		let scaled = |scale: f32| [point[0] / scale as f64, point[1] / scale as f64];
 
-		let water_reading = self.water_gen.get([x as f64, y as f64]) as f32;
+		let water_reading = self.water_gen.get(point) as f32;
 		let waves = Self::WAVE_AMPLITUDE * water_reading - Self::WAVE_BIAS;
 
-		let island_reading = self.island_gen.get([x as f64, y as f64]) as f32;
-		let unclamped_taper = Self::TAPER_SCALE / (1. + x.hypot(y));
-		let taper = unclamped_taper.min(1.0).powf(Self::TAPER_POW);
+		let island_reading = self.island_gen.get(point) as f32;
+
+		//One island around the origin, and more wherever the archipelago
+		//noise clears its level
+		let home = settings.home_island / (1. + x.hypot(y));
+		let archipelago = self.archipelago_gen.get(scaled(settings.archipelago_scale)) as f32;
+		let spread = (archipelago - settings.archipelago_lvl) / settings.archipelago_ramp;
+
+		let unclamped_taper = home.max(spread).clamp(0., 1.);
+		let taper = unclamped_taper.powf(Self::TAPER_POW);
+
+		let biome_pos = scaled(settings.biome_scale);
+		let reef_pos = point.map(|f| f * settings.reef_frequency as f64);
+
+		Reading {
+			height: (1. - taper) * waves + taper * island_reading,
+			temperature: self.temperature_gen.get(biome_pos) as f32,
+			moisture: self.moisture_gen.get(biome_pos) as f32,
+			reef: self.reef_gen.get(reef_pos) as f32,
+		}
+	}
+}
 
-		(1. - taper) * waves + taper * island_reading
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn seeds_near_max_differ() {
+		let readings = |seed| {
+			let noise = Generator::init(seed);
+			(0..16)
+				.map(|i| [i as f64 * 0.37, 1.3])
+				.map(|point| (noise.water_gen.get(point), noise.island_gen.get(point)))
+				.collect::<Vec<_>>()
+		};
+
+		let seeds = [u32::MAX, u32::MAX - 1, u32::MAX - 10, 0];
+		for (i, &a) in seeds.iter().enumerate() {
+			for &b in &seeds[i + 1..] {
+				assert_ne!(
+					readings(a),
+					readings(b),
+					"Seeds {a} and {b} give the same map"
+				);
+			}
+		}
 	}
 }
@@ -1,13 +1,37 @@
-#[derive(Clone, Copy, Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TileKind {
 	Land,
 	Shore,
 	Sea,
 	DeepSea,
 	Wood,
+	//Pier built out from the shore, which boats moor alongside
+	Dock,
+	//Coral just below the surface, which only shallow boats clear
+	Reef,
 }
 
 impl TileKind {
+	pub const MIN_TRAVEL_COST: f32 = 1.;
+
+	//Relative cost of sailing through a tile, None if boats can't
+	pub fn travel_cost(&self) -> Option<f32> {
+		use TileKind::*;
+		match self {
+			Sea | DeepSea => Some(Self::MIN_TRAVEL_COST),
+			Shore => Some(2.5),
+			Reef => Some(4.),
+			Land | Wood | Dock => None,
+		}
+	}
+
+	//As travel_cost, for a boat that can only enter the given kinds
+	pub fn travel_cost_for(&self, navigable: &[TileKind]) -> Option<f32> {
+		self.travel_cost().filter(|_| navigable.contains(self))
+	}
+
 	pub fn color(&self) -> (f32, f32, f32, f32) {
 		use TileKind::*;
 		match self {
@@ -15,7 +39,9 @@ impl TileKind {
 			Shore => (230., 210., 75., 255.),
 			Sea => (57., 120., 168., 255.),
 			DeepSea => (15., 50., 70., 255.),
-			_ => (255., 255., 255., 255.), //Otherwise untinted
+			Dock => (120., 80., 45., 255.),
+			Wood => (25., 85., 45., 255.),
+			Reef => (240., 130., 110., 255.),
 		}
 	}
 }
@@ -1,18 +1,27 @@
+mod biome;
 mod chunk;
+mod delta;
 mod gen;
 mod kinds;
+mod nav;
 mod settings;
+mod survey;
 mod tile;
 
+pub use biome::*;
+pub use chunk::Pier;
+pub use delta::*;
 pub use gen::*;
 pub use kinds::*;
+pub use nav::*;
 pub use settings::*;
+pub use survey::*;
 pub use tile::*;
 
 use super::*;
 use cgmath::*;
 use chunk::*;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
 
 #[cfg(feature = "profile")]
 use tracing::instrument;
@@ -20,13 +29,40 @@ use tracing::instrument;
 pub struct TileMap {
 	settings: TileMapSettings,
 	chunks: FnvHashMap<Vector2<i32>, Task<Chunk>>,
+	//Tiles edited in each chunk, kept even while the chunk isn't loaded
+	deltas: FnvHashMap<Vector2<i32>, ChunkDelta>,
+	//Where boats moor in each chunk generated so far, kept after the
+	//chunk is evicted so docks can be found without generating it again
+	docks: FnvHashMap<Vector2<i32>, Vec<Vector2<f32>>>,
+	//Chunks around boats, as of the latest anchor
+	anchored: FnvHashSet<Vector2<i32>>,
 	noise_fn: Generator,
 	chunks_in_view: [Vector2<i32>; 2],
 }
 
+//Something planned on another thread, along with how many chunks the
+//planner may have to generate for it, which is most of the work
+pub struct Planned<T: Send + 'static> {
+	pub task: Task<T>,
+	pub chunks: usize,
+}
+
+impl<T: Send + 'static> Planned<T> {
+	//Ahead of chunk generation, which would otherwise hold it up
+	const PRIORITY: f32 = 1.;
+
+	fn launch(chunks: usize, f: impl FnOnce() -> T + Send + 'static) -> Self {
+		Self {
+			task: Task::launch_with_priority(Self::PRIORITY, f),
+			chunks,
+		}
+	}
+}
+
 impl TileMap {
 	const PRELOAD_RADIUS: usize = 5;
 	const PREGEN_CHUNK_RAD: i32 = 2;
+	const SWEEP_STEP: f32 = Tile::SIZE / 4.;
 
 	pub fn new(settings: TileMapSettings) -> Self {
 		let rad = Self::PRELOAD_RADIUS as i32;
@@ -37,12 +73,15 @@ impl TileMap {
 		let mut out = Self {
 			settings,
 			chunks: Default::default(),
+			deltas: Default::default(),
+			docks: Default::default(),
+			anchored: Default::default(),
 			noise_fn,
 			chunks_in_view: [-corner, corner],
 		};
 
-		for cx in -rad..rad {
-			for cy in -rad..rad {
+		for cx in -rad..=rad {
+			for cy in -rad..=rad {
 				out.launch_chunk_gen(vec2(cx, cy));
 			}
 		}
@@ -50,15 +89,92 @@ impl TileMap {
 		out
 	}
 
-	pub fn tile(&mut self, tile: Vector2<i32>) -> &mut Tile {
-		self.tile_f(tile.map(|i| i as f32))
+	pub fn settings(&self) -> TileMapSettings {
+		self.settings
 	}
 
-	pub fn tile_f(&mut self, pos: Vector2<f32>) -> &mut Tile {
+	//Swaps in new settings, regenerating every chunk from them starting
+	//with those around the view. Tile edits are kept.
+	#[cfg(feature = "hot-reload")]
+	pub fn regenerate(&mut self, settings: TileMapSettings) {
+		self.settings = settings;
+		self.noise_fn = Generator::init(settings.seed);
+		self.chunks.clear();
+		self.docks.clear();
+
+		let pregen = Self::PREGEN_CHUNK_RAD * vec2(1, 1);
+		let [ll, ur] = self.chunks_in_view;
+		for cx in ll.x - pregen.x..=ur.x + pregen.x {
+			for cy in ll.y - pregen.y..=ur.y + pregen.y {
+				self.launch_chunk_gen(vec2(cx, cy));
+			}
+		}
+	}
+
+	//Changes a tile, recording the change so it outlasts the chunk
+	pub fn edit_tile(&mut self, pos: Vector2<f32>, edit: impl FnOnce(&mut Tile)) {
 		let (chunk_id, tile_id) = Chunk::tile_id(pos);
 		let [i, j] = tile_id.into();
 
-		self.load_chunk(chunk_id).get_tile_mut(i, j)
+		let chunk = self.load_chunk(chunk_id);
+		let tile = chunk.get_tile_mut(i, j);
+		edit(tile);
+
+		let delta = TileDelta::of(tile);
+		chunk.invalidate();
+
+		self.deltas.entry(chunk_id).or_default().set(i, j, delta);
+	}
+
+	//Plans a pier out to sea from the shore tile at pos, as in
+	//Chunk::plan_pier
+	pub fn plan_pier(&mut self, pos: Vector2<f32>) -> Option<Pier> {
+		let (chunk_id, tile_id) = Chunk::tile_id(pos);
+		let [i, j] = tile_id.into();
+
+		let settings = self.settings;
+		self.load_chunk(chunk_id).plan_pier(&settings, i, j)
+	}
+
+	//Builds a planned pier, recording its dock with the tile edits so
+	//boats can moor there after the chunk is regenerated
+	pub fn build_pier(&mut self, pier: Pier) {
+		for &tile in &pier.tiles {
+			self.edit_tile(tile, |tile| tile.build(TileKind::Dock));
+		}
+
+		let cell = Chunk::chunk_id(pier.berth);
+		let delta = self.deltas.entry(cell).or_default();
+		delta.docks.push(pier.berth);
+
+		let chunk = self.load_chunk(cell);
+		chunk.docks.push(pier.berth);
+		let docks = chunk.docks.clone();
+		self.docks.insert(cell, docks);
+	}
+
+	//Every tile edit, by chunk
+	pub fn deltas(&self) -> impl Iterator<Item = (Vector2<i32>, &ChunkDelta)> {
+		self.deltas.iter().map(|(&cell, delta)| (cell, delta))
+	}
+
+	//Reapplies edits from deltas, as saved, to chunks already generated
+	//and any generated later
+	pub fn restore(&mut self, deltas: impl IntoIterator<Item = (Vector2<i32>, ChunkDelta)>) {
+		for (cell, delta) in deltas {
+			//Docks built in chunks not generated yet are found once they are
+			match self.chunks.get_mut(&cell) {
+				Some(task) => {
+					let chunk = task.get_mut();
+					chunk.apply(&delta);
+					self.docks.insert(cell, chunk.docks.clone());
+				}
+				None => {
+					self.docks.remove(&cell);
+				}
+			}
+			self.deltas.insert(cell, delta);
+		}
 	}
 
 	//get tile if it has already been loaded in
@@ -73,15 +189,306 @@ impl TileMap {
 		self.maybe_chunk(chunk_id).map(|chunk| chunk.get_tile(i, j))
 	}
 
+	//Tile at pos, waiting for its chunk if it is still being generated.
+	//None only if the chunk hasn't been launched at all.
+	pub fn generated_tile_f(&self, pos: Vector2<f32>) -> Option<&Tile> {
+		let (chunk_id, tile_id) = Chunk::tile_id(pos);
+		let [i, j] = tile_id.into();
+
+		self.chunks
+			.get(&chunk_id)
+			.map(|task| task.get().get_tile(i, j))
+	}
+
+	//Generates the chunks within radius of each position now, if they
+	//aren't already, so boats there collide with the same tiles however
+	//far along background generation is. These are kept from eviction
+	//until the next anchor. Chunks a little further out are launched in
+	//the background, so they are usually ready in time.
+	pub fn anchor(&mut self, positions: impl IntoIterator<Item = Vector2<f32>>, radius: f32) {
+		let positions: Vec<_> = positions.into_iter().collect();
+
+		let ahead = radius + Chunk::WIDTH / 2.;
+		for &pos in &positions {
+			let lo = Chunk::chunk_id(pos - vec2(ahead, ahead));
+			let hi = Chunk::chunk_id(pos + vec2(ahead, ahead));
+			for cx in lo.x..=hi.x {
+				for cy in lo.y..=hi.y {
+					self.launch_chunk_gen(vec2(cx, cy));
+				}
+			}
+		}
+
+		self.anchored.clear();
+		for &pos in &positions {
+			let lo = Chunk::chunk_id(pos - vec2(radius, radius));
+			let hi = Chunk::chunk_id(pos + vec2(radius, radius));
+			for cx in lo.x..=hi.x {
+				for cy in lo.y..=hi.y {
+					self.load_chunk(vec2(cx, cy));
+					self.anchored.insert(vec2(cx, cy));
+				}
+			}
+		}
+	}
+
+	//Relative cost of sailing through the tile at pos, as in
+	//TileKind::travel_cost. Impassable tiles, and tiles in chunks that
+	//haven't been launched, cost nothing as boats can't be in them.
+	pub fn travel_cost(&self, pos: Vector2<f32>) -> f32 {
+		self.generated_tile_f(pos)
+			.and_then(|tile| tile.kind.travel_cost())
+			.unwrap_or(TileKind::MIN_TRAVEL_COST)
+	}
+
+	//Tiles in chunks that haven't been launched are impassable. Boats
+	//only sail into chunks anchored around them, which always are.
+	pub fn passable(&self, pos: Vector2<f32>, navigable: &[TileKind]) -> bool {
+		self.generated_tile_f(pos)
+			.is_some_and(|tile| tile.kind.travel_cost_for(navigable).is_some())
+	}
+
+	//Furthest point along the segment reachable before entering a tile
+	//that isn't navigable, sampling tiles a fraction of a tile apart.
+	pub fn sweep_line(
+		&self,
+		from: Vector2<f32>,
+		to: Vector2<f32>,
+		navigable: &[TileKind],
+	) -> Vector2<f32> {
+		let samples = (from.distance(to) / Self::SWEEP_STEP).ceil() as usize;
+
+		let mut reached = from;
+		for i in 1..=samples {
+			let p = from.lerp(to, i as f32 / samples as f32);
+			if !self.passable(p, navigable) {
+				break;
+			}
+			reached = p;
+		}
+
+		reached
+	}
+
+	//Moves from one point towards another, sliding along any coast in the
+	//way. Starting on an impassable tile nothing is in the way, so boats
+	//can always leave land they have been placed on.
+	pub fn sweep(
+		&self,
+		from: Vector2<f32>,
+		to: Vector2<f32>,
+		navigable: &[TileKind],
+	) -> Vector2<f32> {
+		if !self.passable(from, navigable) {
+			return to;
+		}
+
+		let reached = self.sweep_line(from, to, navigable);
+		if reached == to {
+			return to;
+		}
+
+		let rest = to - reached;
+		let slide_x = self.sweep_line(reached, reached + vec2(rest.x, 0.), navigable);
+		let slide_y = self.sweep_line(reached, reached + vec2(0., rest.y), navigable);
+
+		if slide_x.distance2(reached) > slide_y.distance2(reached) {
+			slide_x
+		} else {
+			slide_y
+		}
+	}
+
+	//Plans a route around land on another thread. Chunks it may pass
+	//through that aren't generated yet are generated there too.
+	pub fn route(&self, path: Path, navigable: &[TileKind]) -> Planned<Path> {
+		let closing =
+			(path.mode == PathMode::Loop).then(|| (*path.nodes.last().unwrap(), path.nodes[0]));
+		let legs = path
+			.nodes
+			.windows(2)
+			.map(|leg| (leg[0], leg[1]))
+			.chain(closing);
+
+		let mut survey = self.survey();
+		let mut chunks = 0;
+		for (a, b) in legs {
+			let (lo, hi) = NavGrid::bounds(a, b);
+			let (lo, hi) = (NavGrid::center_of(lo), NavGrid::center_of(hi));
+			self.copy_area(&mut survey, lo, hi);
+			chunks += Self::chunks_between(lo, hi);
+		}
+
+		let navigable = navigable.to_vec();
+		Planned::launch(chunks, move || {
+			NavGrid::route(&mut survey, &path, &navigable)
+		})
+	}
+
+	//Plans a route to the nearest dock on another thread, as in
+	//Survey::nearest_dock. Without one in reach the route goes nowhere,
+	//ending where it starts.
+	pub fn route_to_dock(&self, from: Vector2<f32>, navigable: &[TileKind]) -> Planned<Path> {
+		let reach = Chunk::WIDTH * (Survey::DOCK_SEARCH_RADIUS + 1) as f32;
+		let (lo, hi) = (from - vec2(reach, reach), from + vec2(reach, reach));
+		let mut survey = self.survey_area(lo, hi);
+		let navigable = navigable.to_vec();
+
+		Planned::launch(Self::chunks_between(lo, hi), move || {
+			let mut path = Path::new(from);
+			if let Some(dock) = survey.nearest_dock(from) {
+				path.add_waypoint(dock);
+			}
+			NavGrid::route(&mut survey, &path, &navigable)
+		})
+	}
+
+	//Finds the nearest dock to each point on another thread, as in
+	//Survey::nearest_dock
+	pub fn nearest_docks(&self, points: Vec<Vector2<f32>>) -> Planned<Vec<Option<Vector2<f32>>>> {
+		let mut survey = self.survey();
+		let side = 2 * Survey::DOCK_SEARCH_RADIUS as usize + 1;
+		let chunks = points.len() * side * side;
+
+		Planned::launch(chunks, move || {
+			points
+				.into_iter()
+				.map(|point| survey.nearest_dock(point))
+				.collect()
+		})
+	}
+
+	//Closest place to moor within range of pos. Only chunks in range are
+	//searched, generating any that aren't already; those around boats
+	//always are, as they are anchored.
+	pub fn dock_within(&mut self, pos: Vector2<f32>, range: f32) -> Option<Vector2<f32>> {
+		let lo = Chunk::chunk_id(pos - vec2(range, range));
+		let hi = Chunk::chunk_id(pos + vec2(range, range));
+
+		let mut nearest = None;
+		for cx in lo.x..=hi.x {
+			for cy in lo.y..=hi.y {
+				for &dock in &self.load_chunk(vec2(cx, cy)).docks {
+					if dock.distance(pos) < range
+						&& nearest.is_none_or(|best: Vector2<f32>| {
+							dock.distance2(pos) < best.distance2(pos)
+						}) {
+						nearest = Some(dock);
+					}
+				}
+			}
+		}
+
+		nearest
+	}
+
+	//Takes what is known of the map for planning on another thread
+	fn survey(&self) -> Survey {
+		Survey::new(
+			self.settings,
+			self.noise_fn.clone(),
+			self.deltas.clone(),
+			self.docks.clone(),
+		)
+	}
+
+	//As survey, with the tiles of chunks already generated between lo and
+	//hi copied in
+	fn survey_area(&self, lo: Vector2<f32>, hi: Vector2<f32>) -> Survey {
+		let mut survey = self.survey();
+		self.copy_area(&mut survey, lo, hi);
+		survey
+	}
+
+	//Number of chunks covering the area between lo and hi
+	fn chunks_between(lo: Vector2<f32>, hi: Vector2<f32>) -> usize {
+		let (lo, hi) = (Chunk::chunk_id(lo), Chunk::chunk_id(hi));
+		(hi.x - lo.x + 1) as usize * (hi.y - lo.y + 1) as usize
+	}
+
+	fn copy_area(&self, survey: &mut Survey, lo: Vector2<f32>, hi: Vector2<f32>) {
+		let (lo, hi) = (Chunk::chunk_id(lo), Chunk::chunk_id(hi));
+		for cx in lo.x..=hi.x {
+			for cy in lo.y..=hi.y {
+				if let Some(chunk) = self.maybe_chunk(vec2(cx, cy)) {
+					survey.copy(chunk);
+				}
+			}
+		}
+	}
+
 	fn launch_chunk_gen(&mut self, cell: Vector2<i32>) {
+		let priority = self.chunk_priority(cell);
 		let settings = self.settings;
 		let noise = self.noise_fn.clone();
+		let delta = self.deltas.get(&cell).cloned();
 
-		let generate = move || Chunk::generate(settings, cell, &noise);
+		let generate = move || {
+			let mut chunk = Chunk::generate(settings, cell, &noise);
+			if let Some(delta) = delta {
+				chunk.apply(&delta);
+			}
+			chunk
+		};
 
 		self.chunks
 			.entry(cell)
-			.or_insert_with(|| Task::launch(generate));
+			.or_insert_with(|| Task::launch_with_priority(priority, generate));
+	}
+
+	//Chunks nearer the middle of the view are generated first
+	fn chunk_priority(&self, cell: Vector2<i32>) -> f32 {
+		let [ll, ur] = self.chunks_in_view;
+		let center = (ll + ur).map(|i| i as f32) / 2.;
+		-cell.map(|i| i as f32).distance(center)
+	}
+
+	//Chunks waiting to be generated are reprioritized around the view,
+	//and dropped if they have left the area around it
+	fn reschedule(&mut self, keep_lo: Vector2<i32>, keep_hi: Vector2<i32>) {
+		self.chunks.retain(|&cell, task| {
+			task.if_done().is_some()
+				|| (keep_lo.x..=keep_hi.x).contains(&cell.x)
+					&& (keep_lo.y..=keep_hi.y).contains(&cell.y)
+		});
+
+		for (&cell, task) in &self.chunks {
+			if task.if_done().is_none() {
+				task.set_priority(self.chunk_priority(cell));
+			}
+		}
+	}
+
+	//Drops the chunks furthest from the view until within the chunk
+	//budget. Chunks in or near the view, anchored chunks and chunks
+	//still generating are always kept. Edits are kept apart, so they are
+	//reapplied if a chunk is regenerated.
+	fn evict(&mut self, keep_lo: Vector2<i32>, keep_hi: Vector2<i32>) {
+		let budget = self.settings.chunk_budget;
+		if self.chunks.len() <= budget {
+			return;
+		}
+
+		let center = (keep_lo + keep_hi).map(|i| i as f32) / 2.;
+		let kept = |cell: Vector2<i32>| {
+			(keep_lo.x..=keep_hi.x).contains(&cell.x) && (keep_lo.y..=keep_hi.y).contains(&cell.y)
+		};
+
+		let mut evictable: Vec<_> = self
+			.chunks
+			.iter()
+			.filter(|&(cell, task)| {
+				!kept(*cell) && !self.anchored.contains(cell) && task.if_done().is_some()
+			})
+			.map(|(&cell, _)| (cell, cell.map(|i| i as f32).distance2(center)))
+			.collect();
+
+		evictable.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+		let excess = self.chunks.len() - budget;
+		for (cell, _) in evictable.into_iter().take(excess) {
+			self.chunks.remove(&cell);
+		}
 	}
 
 	//return chunk if it has loaded in
@@ -90,10 +497,28 @@ impl TileMap {
 	}
 
 	fn load_chunk(&mut self, cell: Vector2<i32>) -> &mut Chunk {
-		self.chunks
+		let Self {
+			settings,
+			chunks,
+			deltas,
+			docks,
+			noise_fn,
+			..
+		} = self;
+
+		let chunk = chunks
 			.entry(cell)
-			.or_insert_with(|| Task::from_val(Chunk::generate(self.settings, cell, &self.noise_fn)))
-			.get_mut()
+			.or_insert_with(|| {
+				let mut chunk = Chunk::generate(*settings, cell, noise_fn);
+				if let Some(delta) = deltas.get(&cell) {
+					chunk.apply(delta);
+				}
+				Task::from_val(chunk)
+			})
+			.get_mut();
+
+		docks.entry(cell).or_insert_with(|| chunk.docks.clone());
+		chunk
 	}
 }
 
@@ -116,6 +541,9 @@ impl GameObject for TileMap {
 			let gen_uri = uri + Self::PREGEN_CHUNK_RAD * vec2(1, 1);
 
 			let [old_ll, old_ur] = self.chunks_in_view;
+			self.chunks_in_view = [lli, uri];
+			self.reschedule(gen_lli, gen_uri);
+
 			for cx in (gen_lli.x..old_ll.x).chain(old_ur.x..=gen_uri.x) {
 				for cy in gen_lli.y..=gen_uri.y {
 					self.launch_chunk_gen(vec2(cx, cy));
@@ -129,7 +557,16 @@ impl GameObject for TileMap {
 			}
 		}
 
-		self.chunks_in_view = [lli, uri];
+		for (&cell, task) in &self.chunks {
+			if let Some(chunk) = task.if_done() {
+				self.docks
+					.entry(cell)
+					.or_insert_with(|| chunk.docks.clone());
+			}
+		}
+
+		let margin = (Self::PREGEN_CHUNK_RAD + 1) * vec2(1, 1);
+		self.evict(lli - margin, uri + margin);
 
 		None
 	}
@@ -139,7 +576,11 @@ impl GameObject for TileMap {
 		let [ll, ur] = self.chunks_in_view;
 		for cx in ll.x..=ur.x {
 			for cy in ll.y..=ur.y {
-				self.chunks[&vec2(cx, cy)].get().render(win);
+				let cell = vec2(cx, cy);
+				match self.maybe_chunk(cell) {
+					Some(chunk) => chunk.render(win),
+					None => win.queue(Chunk::placeholder(cell, win.external())),
+				}
 			}
 		}
 	}
@@ -160,3 +601,187 @@ impl GameObject for TileMap {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn routes_ignore_generation_progress() {
+		let settings = TileMapSettings::default();
+		let navigable = [TileKind::Sea, TileKind::DeepSea];
+
+		let mut path = Path::new(vec2(0., 0.));
+		path.add_waypoint(vec2(3000., 1500.));
+		path.add_waypoint(vec2(-1000., 2500.));
+
+		//Chunks of one map are still generating in the background, while
+		//those of the other are all done
+		let pending = TileMap::new(settings);
+		let mut done = TileMap::new(settings);
+		for cx in -2..=2 {
+			for cy in -2..=2 {
+				done.load_chunk(vec2(cx, cy));
+			}
+		}
+
+		let a = pending
+			.route(path.clone(), &navigable)
+			.task
+			.get()
+			.nodes
+			.clone();
+		let b = done.route(path, &navigable).task.get().nodes.clone();
+		assert_eq!(a, b);
+	}
+
+	#[test]
+	fn routes_avoid_land() {
+		let map = TileMap::new(TileMapSettings::default());
+		let navigable = [TileKind::Sea, TileKind::DeepSea];
+		let mut crossed = 0;
+
+		//Legs between scattered tiles, at all sorts of angles
+		for k in 0..12 {
+			let from = NavGrid::center_of(vec2(-300 + k * 7, -150 + (k * 13) % 300));
+			let to = NavGrid::center_of(vec2(200 - (k * 11) % 400, 150 - (k * 17) % 300));
+			if !map.passable(from, &navigable) || !map.passable(to, &navigable) {
+				continue;
+			}
+			if map.sweep_line(from, to, &navigable) != to {
+				crossed += 1;
+			}
+
+			let mut path = Path::new(from);
+			path.add_waypoint(to);
+			let route = map.route(path, &navigable).task.get().clone();
+
+			for leg in route.nodes.windows(2) {
+				let samples = (8. * leg[0].distance(leg[1]) / Tile::SIZE).ceil() as usize;
+				for i in 0..=samples {
+					let pos = leg[0].lerp(leg[1], i as f32 / samples.max(1) as f32);
+					assert!(
+						map.passable(pos, &navigable),
+						"Route crosses land at {pos:?}"
+					);
+				}
+			}
+		}
+
+		assert!(crossed > 0, "No direct line crosses land");
+	}
+
+	#[test]
+	fn long_legs_planned_as_far_as_grid_reaches() {
+		let settings = TileMapSettings {
+			home_island: 0.,
+			archipelago_lvl: 1.,
+			..Default::default()
+		};
+		let navigable = [TileKind::Sea, TileKind::DeepSea];
+
+		let goal = vec2(1e6, -1e6);
+		let mut path = Path::new(vec2(0., 0.));
+		path.add_waypoint(goal);
+
+		let planned = TileMap::new(settings).route(path, &navigable);
+		let side = (NavGrid::MAX_SPAN as f32 * Tile::SIZE / Chunk::WIDTH).ceil() as usize + 1;
+		assert!(planned.chunks <= side * side);
+
+		let end = *planned.task.get().nodes.last().unwrap();
+		let reach = NavGrid::MAX_SPAN as f32 * Tile::SIZE;
+		assert!(end.x > 0. && end.x < reach && end.y < 0. && end.y > -reach);
+	}
+
+	#[test]
+	fn anchored_tiles_ignore_generation_progress() {
+		let settings = TileMapSettings::default();
+		let navigable = [TileKind::Sea, TileKind::DeepSea];
+		let far = vec2(40. * Chunk::WIDTH, -25. * Chunk::WIDTH);
+
+		let mut map = TileMap::new(settings);
+		assert!(!map.passable(far, &navigable));
+
+		map.anchor([far], 250.);
+		let mut done = TileMap::new(settings);
+		done.load_chunk(Chunk::chunk_id(far));
+
+		for i in 0..64 {
+			let pos = far + vec2(i as f32, (i * 7 % 64) as f32) * Tile::SIZE;
+			let expected = done.passable(pos, &navigable);
+			assert_eq!(map.passable(pos, &navigable), expected);
+			assert!(map.maybe_tile_f(pos).is_some());
+		}
+	}
+
+	#[test]
+	fn docks_ignore_generation_progress() {
+		let settings = TileMapSettings::default();
+		let points = vec![vec2(0., 0.), vec2(5000., -3000.)];
+
+		let pending = TileMap::new(settings);
+		let mut done = TileMap::new(settings);
+		for cx in -2..=2 {
+			for cy in -2..=2 {
+				done.load_chunk(vec2(cx, cy));
+			}
+		}
+		//Evicted chunks are still known to have their docks
+		done.chunks.remove(&vec2(0, 0));
+
+		let a = pending.nearest_docks(points.clone()).task.get().clone();
+		let b = done.nearest_docks(points).task.get().clone();
+		assert!(a.iter().any(Option::is_some));
+		assert_eq!(a, b);
+	}
+
+	#[test]
+	fn anchored_chunks_outlast_eviction() {
+		let settings = TileMapSettings {
+			chunk_budget: 4,
+			..Default::default()
+		};
+		let far = vec2(40. * Chunk::WIDTH, -25. * Chunk::WIDTH);
+
+		let mut map = TileMap::new(settings);
+		map.anchor([far], 250.);
+		for cx in 0..8 {
+			map.load_chunk(vec2(cx, 0));
+		}
+
+		map.evict(vec2(0, 0), vec2(0, 0));
+		assert!(map.maybe_chunk(Chunk::chunk_id(far)).is_some());
+	}
+
+	#[test]
+	fn built_docks_outlast_regeneration() {
+		let mut map = TileMap::new(TileMapSettings::default());
+		let dim = Chunk::DIMENSION;
+
+		let pier = (0..dim * dim)
+			.map(|k| vec2((k / dim) as f32 + 0.5, (k % dim) as f32 + 0.5) * Tile::SIZE)
+			.find_map(|pos| map.plan_pier(pos))
+			.expect("No shore to build on");
+		let (berth, first) = (pier.berth, pier.tiles[0]);
+		map.build_pier(pier);
+		assert_eq!(map.dock_within(berth, 1.), Some(berth));
+
+		map.chunks.clear();
+		map.docks.clear();
+		assert_eq!(
+			*map.nearest_docks(vec![berth]).task.get(),
+			vec![Some(berth)]
+		);
+		assert_eq!(map.dock_within(berth, 1.), Some(berth));
+		assert_eq!(map.generated_tile_f(first).unwrap().kind, TileKind::Dock);
+	}
+
+	#[test]
+	fn any_seed_generates() {
+		let settings = TileMapSettings {
+			seed: u32::MAX,
+			..Default::default()
+		};
+		TileMap::new(settings).load_chunk(vec2(0, 0));
+	}
+}
@@ -0,0 +1,331 @@
+use super::*;
+use cgmath::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+//Passability of the tiles around one leg of a route for one kind of
+//boat, taken from a Survey so it can be planned off the main thread.
+pub struct NavGrid {
+	//Tile coordinates of the lower left tile
+	origin: Vector2<i32>,
+	width: usize,
+	height: usize,
+	costs: Vec<Option<f32>>,
+}
+
+impl NavGrid {
+	//Tiles of open water kept around the leg's bounding box, so it can
+	//sail around obstacles in the way.
+	const MARGIN: i32 = 48;
+	//Most tiles a grid spans along either axis, which bounds the time it
+	//takes to plan a leg. Longer legs are planned as far as that reaches.
+	pub const MAX_SPAN: i32 = 256;
+
+	pub fn tile_of(pos: Vector2<f32>) -> Vector2<i32> {
+		pos.map(|f| (f / Tile::SIZE).floor() as i32)
+	}
+
+	pub fn center_of(tile: Vector2<i32>) -> Vector2<f32> {
+		tile.map(|i| (i as f32 + 0.5) * Tile::SIZE)
+	}
+
+	//Bounds in tile coordinates, inclusive, covering a leg from a to b,
+	//or as much of it from a as fits within MAX_SPAN
+	pub fn bounds(a: Vector2<f32>, b: Vector2<f32>) -> (Vector2<i32>, Vector2<i32>) {
+		let (a, b) = (Self::tile_of(a), Self::tile_of(b));
+
+		let axis = |a: i32, b: i32| {
+			let (lo, hi) = (a.min(b) - Self::MARGIN, a.max(b) + Self::MARGIN);
+			if hi - lo < Self::MAX_SPAN {
+				(lo, hi)
+			} else if b >= a {
+				(a - Self::MARGIN, a - Self::MARGIN + Self::MAX_SPAN - 1)
+			} else {
+				(a + Self::MARGIN - Self::MAX_SPAN + 1, a + Self::MARGIN)
+			}
+		};
+
+		let (x, y) = (axis(a.x, b.x), axis(a.y, b.y));
+		(vec2(x.0, y.0), vec2(x.1, y.1))
+	}
+
+	pub fn new(
+		survey: &mut Survey,
+		lo: Vector2<i32>,
+		hi: Vector2<i32>,
+		navigable: &[TileKind],
+	) -> Self {
+		survey.explore(lo, hi);
+
+		let width = (hi.x - lo.x + 1) as usize;
+		let height = (hi.y - lo.y + 1) as usize;
+
+		let mut costs = Vec::with_capacity(width * height);
+		for y in lo.y..=hi.y {
+			for x in lo.x..=hi.x {
+				costs.push(survey.kind(vec2(x, y)).travel_cost_for(navigable));
+			}
+		}
+
+		Self {
+			origin: lo,
+			width,
+			height,
+			costs,
+		}
+	}
+
+	//Replaces each leg of the path with a route around impassable tiles,
+	//planned over a grid covering just that leg. A leg ending on land, or
+	//too long for one grid, stops at the closest reachable tile.
+	pub fn route(survey: &mut Survey, path: &Path, navigable: &[TileKind]) -> Path {
+		let mut out = Path::new(path.nodes[0]);
+		out.mode = path.mode;
+
+		//Loops also need a route back to the start. The start itself is
+		//dropped again afterwards, as it is already the first node.
+		let closing = (path.mode == PathMode::Loop).then_some(path.nodes[0]);
+
+		for &goal in path.nodes[1..].iter().chain(&closing) {
+			let from = *out.nodes.last().unwrap();
+			let (lo, hi) = Self::bounds(from, goal);
+			let nav = Self::new(survey, lo, hi, navigable);
+
+			let tiles = nav.search(Self::tile_of(from), Self::tile_of(goal));
+			let reached = tiles.last() == Some(&Self::tile_of(goal));
+
+			let waypoints = nav.smooth(&tiles);
+			for &tile in waypoints.iter().skip(1) {
+				out.add_waypoint(Self::center_of(tile));
+			}
+
+			if reached {
+				if waypoints.len() > 1 {
+					out.move_last(goal);
+				} else {
+					out.add_waypoint(goal);
+				}
+			}
+		}
+
+		if closing.is_some() && out.nodes.len() > 1 {
+			out.nodes.pop();
+		}
+
+		out
+	}
+
+	fn index(&self, tile: Vector2<i32>) -> Option<usize> {
+		let Vector2 { x, y } = tile - self.origin;
+		if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+			None
+		} else {
+			Some(y as usize * self.width + x as usize)
+		}
+	}
+
+	fn tile_at(&self, index: usize) -> Vector2<i32> {
+		self.origin + vec2((index % self.width) as i32, (index / self.width) as i32)
+	}
+
+	fn cost(&self, tile: Vector2<i32>) -> Option<f32> {
+		self.costs[self.index(tile)?]
+	}
+
+	//Octile distance, scaled by the cheapest tile cost
+	fn heuristic(a: Vector2<i32>, b: Vector2<i32>) -> f32 {
+		let dx = (a.x - b.x).abs() as f32;
+		let dy = (a.y - b.y).abs() as f32;
+		TileKind::MIN_TRAVEL_COST * (dx + dy + (std::f32::consts::SQRT_2 - 2.) * dx.min(dy))
+	}
+
+	//A* over tiles, 8-connected without cutting corners of impassable
+	//tiles. Returns the tiles from start to goal, or to the explored tile
+	//closest to the goal if it can't be reached.
+	fn search(&self, start: Vector2<i32>, goal: Vector2<i32>) -> Vec<Vector2<i32>> {
+		let Some(start_index) = self.index(start) else {
+			return vec![start];
+		};
+
+		let n = self.costs.len();
+		let mut g = vec![f32::INFINITY; n];
+		let mut came_from = vec![usize::MAX; n];
+		let mut open = BinaryHeap::new();
+
+		g[start_index] = 0.;
+		open.push(Open {
+			f: Self::heuristic(start, goal),
+			index: start_index,
+		});
+
+		let mut closest = (Self::heuristic(start, goal), start_index);
+
+		while let Some(Open { f, index }) = open.pop() {
+			let tile = self.tile_at(index);
+			let h = Self::heuristic(tile, goal);
+
+			if f > g[index] + h {
+				//Stale entry
+				continue;
+			}
+
+			if h < closest.0 {
+				closest = (h, index);
+			}
+
+			if tile == goal {
+				break;
+			}
+
+			for dx in -1..=1 {
+				for dy in -1..=1 {
+					if dx == 0 && dy == 0 {
+						continue;
+					}
+
+					let next = tile + vec2(dx, dy);
+					let Some(cost) = self.cost(next) else {
+						continue;
+					};
+
+					let diagonal = dx != 0 && dy != 0;
+					if diagonal
+						&& (self.cost(tile + vec2(dx, 0)).is_none()
+							|| self.cost(tile + vec2(0, dy)).is_none())
+					{
+						continue;
+					}
+
+					let step = if diagonal {
+						std::f32::consts::SQRT_2
+					} else {
+						1.
+					};
+
+					let next_index = self.index(next).unwrap();
+					let next_g = g[index] + step * cost;
+					if next_g < g[next_index] {
+						g[next_index] = next_g;
+						came_from[next_index] = index;
+						open.push(Open {
+							f: next_g + Self::heuristic(next, goal),
+							index: next_index,
+						});
+					}
+				}
+			}
+		}
+
+		let mut tiles = vec![];
+		let mut index = closest.1;
+		while index != usize::MAX {
+			tiles.push(self.tile_at(index));
+			index = came_from[index];
+		}
+		tiles.reverse();
+
+		tiles
+	}
+
+	//Drops tiles that can be skipped by sailing in a straight line without
+	//crossing anything costlier than the route it replaces.
+	fn smooth(&self, tiles: &[Vector2<i32>]) -> Vec<Vector2<i32>> {
+		let Some(&first) = tiles.first() else {
+			return vec![];
+		};
+
+		let mut out = vec![first];
+		let mut anchor = 0;
+
+		while anchor + 1 < tiles.len() {
+			let mut next = anchor + 1;
+			let mut route_cost = 0.;
+
+			for j in anchor + 1..tiles.len() {
+				let from = tiles[j - 1];
+				let step = (tiles[j] - from).map(|i| i as f32).magnitude();
+				route_cost += step * self.cost(tiles[j]).unwrap_or(f32::INFINITY);
+
+				match self.line_cost(tiles[anchor], tiles[j]) {
+					Some(cost) if cost <= route_cost + f32::EPSILON * route_cost.max(1.) => {
+						next = j
+					}
+					_ => break,
+				}
+			}
+
+			out.push(tiles[next]);
+			anchor = next;
+		}
+
+		out
+	}
+
+	//Cost of sailing straight between tile centers, through every tile the
+	//line touches, weighted by the length of line within each. Lines
+	//through a corner need both tiles beside it, as in search.
+	fn line_cost(&self, a: Vector2<i32>, b: Vector2<i32>) -> Option<f32> {
+		let d = (b - a).map(|i| i as f32);
+		let length = d.magnitude();
+		let step = (b - a).map(i32::signum);
+
+		//Fraction of the line between crossing tile edges, and to the
+		//first crossing from the center of a
+		let delta = d.map(|f| 1. / f.abs());
+		let mut next = delta / 2.;
+
+		let mut tile = a;
+		let mut t = 0.;
+		let mut cost = 0.;
+		loop {
+			let edge = next.x.min(next.y).min(1.);
+			cost += self.cost(tile)? * (edge - t) * length;
+			if edge >= 1. {
+				return Some(cost);
+			}
+			t = edge;
+
+			if next.x < next.y {
+				tile.x += step.x;
+				next.x += delta.x;
+			} else if next.y < next.x {
+				tile.y += step.y;
+				next.y += delta.y;
+			} else {
+				self.cost(tile + vec2(step.x, 0))?;
+				self.cost(tile + vec2(0, step.y))?;
+				tile += step;
+				next += delta;
+			}
+		}
+	}
+}
+
+struct Open {
+	f: f32,
+	index: usize,
+}
+
+impl PartialEq for Open {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+//Reversed so the heap pops the lowest estimate first
+impl Ord for Open {
+	fn cmp(&self, other: &Self) -> Ordering {
+		other
+			.f
+			.total_cmp(&self.f)
+			.then_with(|| other.index.cmp(&self.index))
+	}
+}
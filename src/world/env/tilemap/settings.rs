@@ -1,4 +1,13 @@
-#[derive(Clone, Copy)]
+use super::Palettes;
+use fnv::FnvHashMap;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[cfg(feature = "hot-reload")]
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TileMapSettings {
 	pub seed: u32,
 	pub height_pow: f32,
@@ -10,12 +19,126 @@ pub struct TileMapSettings {
 	pub sea_floor_lvl: f32,
 	pub dock_prob: f32,
 	pub dock_depth: f32,
+	//Size of the island at the origin, 0 for none
+	pub home_island: f32,
+	//Size of the regions islands cluster in, relative to scale
+	pub archipelago_scale: f32,
+	//Noise level archipelagos form above. The noise mostly lies within
+	//-0.5 to 0.5, so higher is rarer.
+	pub archipelago_lvl: f32,
+	//How far above archipelago_lvl islands reach full size
+	pub archipelago_ramp: f32,
+	//Size of climate regions, relative to scale
+	pub biome_scale: f32,
+	//Temperatures, on the same scale, below which land is arctic and above
+	//which it is tropical or, if drier than dry_lvl, desert
+	pub cold_lvl: f32,
+	pub hot_lvl: f32,
+	pub dry_lvl: f32,
+	//Moisture above which land grows forests
+	pub forest_lvl: f32,
+	//Reef noise above which tropical shallows have reefs
+	pub reef_lvl: f32,
+	//Frequency of reef noise, relative to scale. Higher makes for smaller,
+	//more scattered patches of reef.
+	pub reef_frequency: f32,
+	//Colours of tiles in each biome
+	pub palettes: Palettes,
+	//Most chunks kept in memory before those furthest from view are
+	//dropped, to be regenerated if needed again
+	pub chunk_budget: usize,
+}
+
+//Named world generation settings, as loaded from Preset::PATH
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+	//Worlds are seeded at random unless the preset picks a seed
+	pub seed: Option<u32>,
+	pub settings: TileMapSettings,
+}
+
+impl Preset {
+	pub const PATH: &'static str = "assets/worlds.ron";
+	pub const DEFAULT: &'static str = "default";
+
+	pub fn load(path: impl AsRef<std::path::Path>, name: &str) -> io::Result<Self> {
+		let file = std::fs::File::open(path)?;
+		let mut presets: FnvHashMap<String, Self> = ron::de::from_reader(io::BufReader::new(file))
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+		presets.remove(name).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("no world preset named {}", name),
+			)
+		})
+	}
+
+	//Settings seeded by the preset, or else with the given seed
+	pub fn seeded(&self, seed: u32) -> TileMapSettings {
+		TileMapSettings {
+			seed: self.seed.unwrap_or(seed),
+			..self.settings
+		}
+	}
+}
+
+//Reloads a preset whenever its file changes
+#[cfg(feature = "hot-reload")]
+pub struct PresetWatch {
+	name: String,
+	modified: Option<SystemTime>,
+	//Updates until the file is checked again
+	wait: u32,
+}
+
+#[cfg(feature = "hot-reload")]
+impl PresetWatch {
+	const CHECK_INTERVAL: u32 = 30;
+
+	pub fn new(name: &str) -> Self {
+		Self {
+			name: name.into(),
+			modified: Self::modified(),
+			wait: Self::CHECK_INTERVAL,
+		}
+	}
+
+	fn modified() -> Option<SystemTime> {
+		std::fs::metadata(Preset::PATH)
+			.and_then(|meta| meta.modified())
+			.ok()
+	}
+
+	//The preset as it is now, if the file has changed since last checked
+	pub fn poll(&mut self) -> Option<Preset> {
+		if self.wait > 0 {
+			self.wait -= 1;
+			return None;
+		}
+		self.wait = Self::CHECK_INTERVAL;
+
+		let modified = Self::modified();
+		if modified == self.modified {
+			return None;
+		}
+		self.modified = modified;
+
+		match Preset::load(Preset::PATH, &self.name) {
+			Ok(preset) => Some(preset),
+			Err(err) => {
+				println!("unable to reload world preset: {}", err);
+				None
+			}
+		}
+	}
 }
 
 impl Default for TileMapSettings {
 	fn default() -> Self {
 		Self {
-			seed: rand::random(),
+			seed: 0,
 			height_pow: 1.,
 			scale: 3000.,
 			land_lvl: 0.20,
@@ -25,6 +148,19 @@ impl Default for TileMapSettings {
 			sea_floor_lvl: -0.3,
 			dock_prob: 0.10,
 			dock_depth: 0.025,
+			home_island: 2.5,
+			archipelago_scale: 6.,
+			archipelago_lvl: 0.2,
+			archipelago_ramp: 0.15,
+			biome_scale: 8.,
+			cold_lvl: -0.35,
+			hot_lvl: 0.25,
+			dry_lvl: -0.15,
+			forest_lvl: 0.2,
+			reef_lvl: 0.3,
+			reef_frequency: 4.,
+			palettes: Palettes::default(),
+			chunk_budget: 512,
 		}
 	}
 }
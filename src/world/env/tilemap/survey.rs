@@ -0,0 +1,114 @@
+use super::*;
+use cgmath::*;
+use chunk::*;
+use fnv::FnvHashMap;
+use rayon::prelude::*;
+
+//What a TileMap knows, taken so plans can be made on another thread.
+//Chunks the map had already generated are copied in, and any others are
+//generated here as needed, so plans don't depend on how far along
+//background generation is.
+pub struct Survey {
+	settings: TileMapSettings,
+	noise_fn: Generator,
+	deltas: FnvHashMap<Vector2<i32>, ChunkDelta>,
+	//Tile kinds of each chunk, indexed as in Chunk::get_tile
+	kinds: FnvHashMap<Vector2<i32>, Vec<TileKind>>,
+	docks: FnvHashMap<Vector2<i32>, Vec<Vector2<f32>>>,
+}
+
+impl Survey {
+	//Chunks around a point searched for docks
+	pub(super) const DOCK_SEARCH_RADIUS: i32 = 3;
+
+	pub(super) fn new(
+		settings: TileMapSettings,
+		noise_fn: Generator,
+		deltas: FnvHashMap<Vector2<i32>, ChunkDelta>,
+		docks: FnvHashMap<Vector2<i32>, Vec<Vector2<f32>>>,
+	) -> Self {
+		Self {
+			settings,
+			noise_fn,
+			deltas,
+			kinds: Default::default(),
+			docks,
+		}
+	}
+
+	pub(super) fn copy(&mut self, chunk: &Chunk) {
+		self.kinds.insert(chunk.cell_pos, chunk.kinds());
+		self.docks.insert(chunk.cell_pos, chunk.docks.clone());
+	}
+
+	//Closest place to moor at a dock, searching chunks within
+	//DOCK_SEARCH_RADIUS of pos
+	pub fn nearest_dock(&mut self, pos: Vector2<f32>) -> Option<Vector2<f32>> {
+		let rad = Self::DOCK_SEARCH_RADIUS;
+		let center = Chunk::chunk_id(pos);
+
+		let cells: Vec<_> = (-rad..=rad)
+			.flat_map(|cx| (-rad..=rad).map(move |cy| center + vec2(cx, cy)))
+			.collect();
+
+		let missing = cells
+			.iter()
+			.copied()
+			.filter(|cell| !self.docks.contains_key(cell))
+			.collect();
+		self.generate(missing);
+
+		cells
+			.iter()
+			.flat_map(|cell| &self.docks[cell])
+			.copied()
+			.min_by(|a, b| a.distance2(pos).total_cmp(&b.distance2(pos)))
+	}
+
+	//Generates every chunk between the tiles lo and hi, inclusive, that
+	//isn't known yet
+	pub fn explore(&mut self, lo: Vector2<i32>, hi: Vector2<i32>) {
+		let dim = Chunk::DIMENSION as i32;
+		let (lo, hi) = (lo.map(|i| i.div_euclid(dim)), hi.map(|i| i.div_euclid(dim)));
+
+		let missing = (lo.x..=hi.x)
+			.flat_map(|cx| (lo.y..=hi.y).map(move |cy| vec2(cx, cy)))
+			.filter(|cell| !self.kinds.contains_key(cell))
+			.collect();
+		self.generate(missing);
+	}
+
+	//Kind of the tile at tile coordinates, which must have been explored
+	pub fn kind(&self, tile: Vector2<i32>) -> TileKind {
+		let dim = Chunk::DIMENSION as i32;
+		let cell = tile.map(|i| i.div_euclid(dim));
+		let [i, j] = tile.map(|i| i.rem_euclid(dim) as usize).into();
+
+		self.kinds[&cell][i * Chunk::DIMENSION + j]
+	}
+
+	fn generate(&mut self, cells: Vec<Vector2<i32>>) {
+		let Self {
+			settings,
+			noise_fn,
+			deltas,
+			..
+		} = &*self;
+
+		let generated: Vec<_> = cells
+			.into_par_iter()
+			.map(|cell| {
+				let mut chunk = Chunk::generate(*settings, cell, noise_fn);
+				if let Some(delta) = deltas.get(&cell) {
+					chunk.apply(delta);
+				}
+				(cell, chunk.kinds(), chunk.docks)
+			})
+			.collect();
+
+		for (cell, kinds, docks) in generated {
+			self.kinds.insert(cell, kinds);
+			self.docks.insert(cell, docks);
+		}
+	}
+}
@@ -11,8 +11,9 @@ pub struct Tile {
 impl Tile {
 	pub const SIZE: f32 = 32.;
 
-	pub fn generate_geography(settings: &TileMapSettings, reading: f32) -> Self {
-		let height = reading.abs().powf(settings.height_pow) * reading.signum();
+	pub fn generate_geography(settings: &TileMapSettings, reading: &Reading) -> Self {
+		let height = reading.height.abs().powf(settings.height_pow) * reading.height.signum();
+		let biome = Biome::of(settings, reading);
 
 		let boundaries = [
 			(TileKind::Land, settings.land_lvl),
@@ -30,8 +31,8 @@ impl Tile {
 		let mut color = vec4(0., 0., 0., 0.);
 
 		for (tile_kind, boundary) in boundaries {
-			let lower_color: Vector4<f32> = tile_kind.color().into();
-			let upper_color: Vector4<f32> = upper_kind.color().into();
+			let lower_color: Vector4<f32> = biome.color(settings, tile_kind).into();
+			let upper_color: Vector4<f32> = biome.color(settings, upper_kind).into();
 			if height > boundary {
 				kind = Some(tile_kind);
 
@@ -50,7 +51,31 @@ impl Tile {
 		let kind = kind.unwrap();
 		let tint = (color / 255.).into();
 
-		Self { height, kind, tint }
+		let mut tile = Self { height, kind, tint };
+
+		//Forests cover wet land, and reefs grow in shallow warm water
+		let forest =
+			kind == TileKind::Land && biome.has_forests() && reading.moisture > settings.forest_lvl;
+		let reef = kind == TileKind::Sea && biome.has_reefs() && reading.reef > settings.reef_lvl;
+
+		if forest {
+			tile.build_in(settings, biome, TileKind::Wood);
+		} else if reef {
+			tile.build_in(settings, biome, TileKind::Reef);
+		}
+
+		tile
+	}
+
+	//Turns the tile into another kind, in that kind's flat colour
+	pub fn build(&mut self, kind: TileKind) {
+		self.kind = kind;
+		self.tint = (Vector4::from(kind.color()) / 255.).into();
+	}
+
+	fn build_in(&mut self, settings: &TileMapSettings, biome: Biome, kind: TileKind) {
+		self.kind = kind;
+		self.tint = (Vector4::from(biome.color(settings, kind)) / 255.).into();
 	}
 
 	pub fn instance(&self, external: &External) -> Instance {
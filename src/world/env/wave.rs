@@ -18,22 +18,22 @@ impl Wave {
 	const DENSITY: f32 = 1. / 500_000.;
 	const SPAWN_MARGIN: f32 = 1.5;
 
-	pub fn maybe_spawn(map: &mut TileMap, external: &External) -> Option<Self> {
+	pub fn maybe_spawn(map: &mut TileMap, external: &External, rng: &mut Rng) -> Option<Self> {
 		let v = external.view_dims() / 2.;
 		let cam = external.camera.pos;
-		let offset = v.map(|f| rand_in(-f, f)) * Self::SPAWN_MARGIN;
+		let offset = v.map(|f| rng.rand_in(-f, f)) * Self::SPAWN_MARGIN;
 		let pos = cam + offset;
 
 		let tile_id = snap_to_grid(pos, (Tile::SIZE, Tile::SIZE));
 
-		if probability(Self::DENSITY * external.delta * v.x * v.y)
+		if rng.probability(Self::DENSITY * external.delta * v.x * v.y)
 			&& map
 				.maybe_tile(tile_id)
 				.is_some_and(|tile| tile.kind == TileKind::DeepSea)
 		{
 			Some(Wave {
 				pos,
-				animation: Animation::new(Texture::Wave, 4., curves::SIN_SQ, 1.0),
+				animation: Animation::new(external.now, Texture::Wave, 4., curves::SIN_SQ, 1.0),
 			})
 		} else {
 			None
@@ -1,24 +1,70 @@
 mod boats;
 mod env;
+mod save;
 mod signal;
 mod texture;
+mod trade;
 mod ui;
 
 use crate::window::*;
 use boats::*;
 use env::*;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use trade::*;
 
 #[cfg(feature = "profile")]
 use tracing::instrument;
 
 pub use super::eng::*;
+pub use save::Save;
 pub use signal::Signal;
 pub use texture::Texture;
 pub use ui::*;
 
+//How a world is set up when the game starts
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+	//Preset in Preset::PATH to generate the world from, if not the default
+	pub preset: Option<String>,
+	//Save to start from, read in full so replays don't depend on the file
+	pub load: Option<Box<Save>>,
+}
+
 pub struct World {
 	pub env: Environment,
 	pub ui: WorldUI,
+	//Camera restored from a save, applied on the next call to camera
+	loaded_camera: Cell<Option<Camera>>,
+	//Regenerates the world when its preset is changed. Kept apart from
+	//the environment, so it outlasts loading a save.
+	#[cfg(feature = "hot-reload")]
+	preset: PresetWatch,
+}
+
+impl World {
+	pub fn save(&self, camera: Camera) -> Save {
+		Save::new(self, camera)
+	}
+
+	pub fn load(&mut self, save: Save) {
+		self.env = Environment::with_settings(save.tiles);
+		self.env.tiles.restore(save.tile_edits);
+		for boat in save.boats {
+			self.env.boats.insert(boat);
+		}
+
+		self.env.treasury = save.treasury;
+		for market in save.markets {
+			let dock = NavGrid::tile_of(market.pos);
+			self.env.markets.insert(dock, market);
+		}
+
+		//Any action in progress may refer to boats which no longer exist
+		self.ui = WorldUI::new();
+		self.loaded_camera.set(Some(save.camera));
+	}
 }
 
 impl Root for World {
@@ -26,12 +72,34 @@ impl Root for World {
 
 	type Texture = Texture;
 	type Signal = Signal;
+	type Options = Options;
+
+	fn parse_args() -> Options {
+		Options {
+			//Pass `--preset <name>` to generate the world from another preset
+			preset: arg("--preset"),
+			//Pass `--load <file>` to start from a save
+			load: arg("--load")
+				.map(|path| Box::new(Save::read(path).expect("Unable to load save."))),
+		}
+	}
+
+	fn init(external: &External, options: &Options) -> Self {
+		let preset = options.preset.as_deref().unwrap_or(Preset::DEFAULT);
 
-	fn init(_external: &External) -> Self {
-		Self {
-			env: Environment::new(),
+		let mut world = Self {
+			env: Environment::new(&external.rng, preset),
 			ui: WorldUI::new(),
+			loaded_camera: None.into(),
+			#[cfg(feature = "hot-reload")]
+			preset: PresetWatch::new(preset),
+		};
+
+		if let Some(save) = &options.load {
+			world.load((**save).clone());
 		}
+
+		world
 	}
 
 	fn camera(&self, inputs: &External) -> Camera {
@@ -43,7 +111,7 @@ impl Root for World {
 			[Q, Z, W, A, S, D].map(|k| if inputs.key(k).is_down() { 1 } else { -1 })
 		};
 
-		let mut camera = inputs.camera;
+		let mut camera = self.loaded_camera.take().unwrap_or(inputs.camera);
 		camera.scale += CAM_SCALE_SPEED * inputs.delta * (q - z) as f32;
 		camera.pos.x += CAM_MOVE_SPEED * inputs.delta * (d - a) as f32;
 		camera.pos.y += CAM_MOVE_SPEED * inputs.delta * (w - s) as f32;
@@ -59,6 +127,32 @@ impl Root for World {
 
 	#[cfg_attr(feature = "profile", instrument(skip_all, name = "Updating World"))]
 	fn update(&mut self, external: &External, messenger: &Messenger<Signal>) {
+		use winit::event::VirtualKeyCode::*;
+
+		if external.key(F5).pressed() {
+			match self.save(external.camera).write(Save::QUICKSAVE) {
+				Ok(()) => println!("saved to {}", Save::QUICKSAVE),
+				Err(err) => println!("unable to save: {}", err),
+			}
+		}
+
+		//The quicksave may have changed by the time a replay is watched
+		if external.key(F9).pressed() && external.recording {
+			println!("unable to load while recording");
+		} else if external.key(F9).pressed() {
+			match Save::read(Save::QUICKSAVE) {
+				Ok(save) => self.load(save),
+				Err(err) => println!("unable to load: {}", err),
+			}
+		}
+
+		#[cfg(feature = "hot-reload")]
+		if let Some(preset) = self.preset.poll() {
+			println!("reloaded world preset");
+			let seed = self.env.tiles.settings().seed;
+			self.env.tiles.regenerate(preset.seeded(seed));
+		}
+
 		self.env.update(external, messenger);
 		if let Some(action) = self.ui.update(external, messenger) {
 			self.env.act(action);
@@ -75,3 +169,385 @@ impl Root for World {
 		self.env.cleanup();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::eng::state::GameState;
+	use cgmath::*;
+
+	//Open sea everywhere, so routes are straight lines
+	fn ocean() -> Environment {
+		Environment::with_settings(TileMapSettings {
+			home_island: 0.,
+			archipelago_lvl: 1.,
+			..Default::default()
+		})
+	}
+
+	fn ocean_game() -> GameState<World> {
+		let mut game = GameState::<World>::headless((800, 600), 1, Default::default());
+		game.world_mut().env = ocean();
+		game
+	}
+
+	//Window pixel the camera shows pos at
+	fn pixel(game: &GameState<World>, pos: Vector2<f32>) -> InputEvent {
+		let external = game.external();
+		let (sx, sy) = (external.win_size.0 as f32, external.win_size.1 as f32);
+		let p = (pos - external.camera.pos) / external.camera.scale;
+		let x = (p.x * sy / sx + 1.) * sx / 2.;
+		let y = (1. - p.y) * sy / 2.;
+		InputEvent::CursorMoved(x as f64, y as f64)
+	}
+
+	fn press(game: &mut GameState<World>, event: impl Fn(bool) -> InputEvent) {
+		game.input(event(true));
+		game.advance(1. / 60.);
+		game.input(event(false));
+		game.advance(1. / 60.);
+	}
+
+	#[test]
+	fn routing_by_mouse_replays_identically() {
+		use winit::event::{MouseButton::*, VirtualKeyCode::Space};
+
+		let mut game = ocean_game();
+		game.record();
+
+		let start = vec2(-300., -200.);
+		let goal = vec2(500., 300.);
+
+		//Place a raft, click it to start a route, click where it should go
+		//and confirm the route
+		game.input(pixel(&game, start));
+		press(&mut game, |down| InputEvent::MouseButton(Right, down));
+		press(&mut game, |down| InputEvent::MouseButton(Left, down));
+		game.input(pixel(&game, goal));
+		press(&mut game, |down| InputEvent::MouseButton(Left, down));
+		press(&mut game, |down| InputEvent::Key(Space, down));
+
+		for _ in 0..600 {
+			game.advance(1. / 60.);
+		}
+
+		let sailed: Vec<_> = game.world().env.boats.iter().map(|boat| boat.pos).collect();
+		assert_eq!(sailed.len(), 1);
+		assert!(sailed[0].distance(goal) < 50., "boat at {:?}", sailed[0]);
+
+		let path = std::env::temp_dir().join("seas_routing_replay.ron");
+		game.stop_recording().unwrap().save(&path).unwrap();
+		let replay = Replay::load(&path).unwrap();
+
+		let mut replayed = GameState::<World>::from_replay(&replay);
+		replayed.world_mut().env = ocean();
+		replayed.play_back(&replay);
+
+		let replayed: Vec<_> = replayed
+			.world()
+			.env
+			.boats
+			.iter()
+			.map(|boat| boat.pos)
+			.collect();
+		assert_eq!(sailed, replayed);
+	}
+
+	#[test]
+	fn headless_boat_sails_route() {
+		let mut game = ocean_game();
+
+		let start = vec2(0., 0.);
+		let goal = vec2(1500., 500.);
+
+		let env = &mut game.world_mut().env;
+		env.act(UIAction::Place(start, 0));
+		let (id, _) = env.boats.iter_with_ids().next().unwrap();
+
+		let mut path = Path::new(start);
+		path.add_waypoint(goal);
+		env.act(UIAction::Route(vec![id], path, Formation::default()));
+
+		//Look where the boat is headed, so it is drawn when it gets there
+		game.external_mut().camera.pos = goal;
+		for _ in 0..600 {
+			game.advance(1. / 60.);
+		}
+
+		let boat = game.world().env.boats.get(id).unwrap();
+		assert!(boat.pos.distance(goal) < 50., "boat at {:?}", boat.pos);
+		assert!(boat.path.is_none());
+
+		let recording = game.recording().unwrap();
+		let drawn = recording
+			.queued()
+			.iter()
+			.any(|inst| vec2(inst.position.0, inst.position.1).distance(boat.pos) < 1.);
+		assert!(drawn);
+		assert!(recording.cached_draws() > 0);
+	}
+
+	#[test]
+	fn tab_shows_kind_placed() {
+		use winit::event::VirtualKeyCode::Tab;
+
+		let mut game = ocean_game();
+		let names: Vec<_> = game
+			.world()
+			.env
+			.kinds
+			.iter()
+			.map(|kind| kind.name.clone())
+			.collect();
+
+		game.advance(1. / 60.);
+		assert!(game.title().ends_with(&names[0]), "{}", game.title());
+
+		press(&mut game, |down| InputEvent::Key(Tab, down));
+		assert!(game.title().ends_with(&names[1]), "{}", game.title());
+	}
+
+	#[test]
+	fn boats_slow_on_arrival() {
+		let mut game = ocean_game();
+		let goal = vec2(1500., 0.);
+
+		let env = &mut game.world_mut().env;
+		env.act(UIAction::Place(vec2(0., 0.), 0));
+		let (id, _) = env.boats.iter_with_ids().next().unwrap();
+		let mut path = Path::new(vec2(0., 0.));
+		path.add_waypoint(goal);
+		env.act(UIAction::Route(vec![id], path, Formation::default()));
+
+		//Distance sailed each update, and how far from the goal it was
+		let mut steps = vec![];
+		for _ in 0..1200 {
+			let before = game.world().env.boats.get(id).unwrap().pos;
+			game.advance(1. / 60.);
+			let after = game.world().env.boats.get(id).unwrap().pos;
+			steps.push((after.distance(before), after.distance(goal)));
+		}
+
+		let cruising = steps.iter().map(|&(step, _)| step).fold(0., f32::max);
+		let (arriving, _) = steps.iter().find(|&&(_, left)| left < 50.).unwrap();
+		assert!(
+			*arriving < cruising / 2.,
+			"{arriving} at full speed {cruising}"
+		);
+	}
+
+	#[test]
+	fn boat_stops_at_shore() {
+		let mut game = GameState::<World>::headless((800, 600), 1, Default::default());
+		let env = &mut game.world_mut().env;
+		env.act(UIAction::Place(vec2(0., 0.), 0));
+		let (id, boat) = env.boats.iter_with_ids().next().unwrap();
+		let navigable = boat.kind.navigable.clone();
+
+		//Sail straight at a coast from a little way out to sea
+		let tile = |x| NavGrid::center_of(vec2(x, 0));
+		let sea = |env: &Environment, x| env.tiles.passable(tile(x), &navigable);
+		let land = |x| (x..x + 30).all(|x| !sea(env, x));
+		let coast = (0..)
+			.find(|&x| (x - 8..x).all(|x| sea(env, x)) && land(x))
+			.unwrap();
+		let start = coast - 8;
+
+		env.boats.move_to(id, tile(start));
+		let mut path = Path::new(tile(start));
+		path.add_waypoint(tile(coast + 100));
+		env.boats.get_mut(id).unwrap().follow(path);
+
+		for _ in 0..1200 {
+			game.advance(1. / 60.);
+		}
+
+		let env = &game.world().env;
+		let boat = env.boats.get(id).unwrap();
+		assert!(boat.aground && boat.path.is_none());
+		let near = tile(coast + 5).x - tile(coast).x;
+		assert!(
+			boat.pos.distance(tile(coast)) < near,
+			"boat at {:?}",
+			boat.pos
+		);
+		assert!(env.tiles.passable(boat.pos, &navigable));
+
+		let stuck = boat.pos;
+		game.advance(1.);
+		assert_eq!(game.world().env.boats.get(id).unwrap().pos, stuck);
+	}
+
+	#[test]
+	fn trades_limited_by_hold_and_treasury() {
+		let mut game = GameState::<World>::headless((800, 600), 1, Default::default());
+		let env = &mut game.world_mut().env;
+		let (sender, _receiver) = std::sync::mpsc::channel();
+
+		let dock = env.tiles.nearest_docks(vec![vec2(0., 0.)]).task.get()[0];
+		let dock = dock.expect("No dock near the origin");
+		let market = env.dock_near(dock).unwrap();
+		let good = env.markets[&market].produces;
+		let other = Good::all().find(|&other| other != good).unwrap();
+
+		env.act(UIAction::Place(dock, 0));
+		let (id, _) = env.boats.iter_with_ids().next().unwrap();
+		let load = [Order::Load(good, None)];
+
+		//Room for just one more unit
+		let boat = env.boats.get_mut(id).unwrap();
+		let space = boat.space();
+		boat.cargo.add(other, space - 1);
+
+		env.trade(id, &load, &sender);
+		let boat = env.boats.get(id).unwrap();
+		assert_eq!((boat.cargo.get(good), boat.space()), (1, 0));
+
+		let (cargo, treasury) = (boat.cargo, env.treasury);
+		env.trade(id, &load, &sender);
+		assert_eq!(env.boats.get(id).unwrap().cargo, cargo);
+		assert_eq!(env.treasury, treasury);
+
+		//Room in the hold, but nothing to pay with
+		env.boats.get_mut(id).unwrap().cargo = Cargo::default();
+		env.treasury = 0.;
+		env.trade(id, &load, &sender);
+		assert!(env.boats.get(id).unwrap().cargo.is_empty());
+		assert_eq!(env.treasury, 0.);
+	}
+
+	#[test]
+	fn itineraries_move_cargo_and_money() {
+		let mut game = GameState::<World>::headless((800, 600), 1, Default::default());
+		let env = &mut game.world_mut().env;
+
+		//The two docks closest together around the origin
+		let around: Vec<_> = (-2..=2)
+			.flat_map(|x| (-2..=2).map(move |y| vec2(x as f32, y as f32) * 4000.))
+			.collect();
+		let mut docks: Vec<_> = env
+			.tiles
+			.nearest_docks(around)
+			.task
+			.get()
+			.iter()
+			.flatten()
+			.copied()
+			.collect();
+		docks.sort_by(|a, b| (a.x, a.y).partial_cmp(&(b.x, b.y)).unwrap());
+		docks.dedup();
+		let (from, to) = docks
+			.iter()
+			.flat_map(|&a| docks.iter().map(move |&b| (a, b)))
+			.filter(|(a, b)| a != b)
+			.min_by(|(a, b), (c, d)| a.distance(*b).total_cmp(&c.distance(*d)))
+			.expect("Not enough docks to trade between");
+
+		let (from_key, to_key) = (env.dock_near(from).unwrap(), env.dock_near(to).unwrap());
+		let good = env.markets[&from_key].produces;
+		let itinerary = Itinerary::new(vec![
+			Stop {
+				dock: from,
+				orders: vec![Order::Load(good, None)],
+			},
+			Stop {
+				dock: to,
+				orders: vec![Order::Unload(good, None)],
+			},
+		])
+		.unwrap();
+
+		//Start at the first stop, as if having just sailed there
+		env.act(UIAction::Place(from, 0));
+		let (id, boat) = env.boats.iter_with_ids().next().unwrap();
+		let capacity = boat.kind.capacity;
+		let boat = env.boats.get_mut(id).unwrap();
+		boat.itinerary = Some(itinerary);
+		let mut path = Path::new(from);
+		path.add_waypoint(from);
+		boat.follow(path);
+
+		let stock = |game: &GameState<World>, key| game.world().env.markets[&key].stock(good);
+		let start_treasury = game.world().env.treasury;
+
+		//Loaded at the first stop
+		let loaded = (0..600).find(|_| {
+			game.advance(1. / 60.);
+			game.world().env.boats.get(id).unwrap().cargo.get(good) > 0
+		});
+		assert!(loaded.is_some(), "Nothing loaded");
+		let env = &game.world().env;
+		assert_eq!(env.boats.get(id).unwrap().cargo.get(good), capacity);
+		let paid = start_treasury - env.treasury;
+		assert!(paid > 0.);
+
+		//And sold at the second, all at once
+		let mut before = (0., 0.);
+		let sold = (0..6000).find(|_| {
+			before = (game.world().env.treasury, stock(&game, to_key));
+			game.advance(1. / 60.);
+			game.world().env.boats.get(id).unwrap().cargo.is_empty()
+		});
+		assert!(sold.is_some(), "Nothing unloaded");
+		let env = &mut game.world_mut().env;
+		let pos = env.boats.get(id).unwrap().pos;
+		assert_eq!(env.dock_near(pos), Some(to_key));
+		assert!(env.treasury > before.0);
+		assert!((stock(&game, to_key) - before.1 - capacity as f32).abs() < 0.1);
+	}
+
+	#[test]
+	fn docking_by_hand_does_not_trade() {
+		let mut game = GameState::<World>::headless((800, 600), 1, Default::default());
+		let env = &mut game.world_mut().env;
+
+		let dock = env.tiles.nearest_docks(vec![vec2(0., 0.)]).task.get()[0];
+		let dock = dock.expect("No dock near the origin");
+		let market = env.dock_near(dock).unwrap();
+		let wanted = env.markets[&market].demands;
+
+		//Near enough to sail to the dock, and carrying what it wants
+		env.act(UIAction::Place(dock, 0));
+		let (id, _) = env.boats.iter_with_ids().next().unwrap();
+		env.boats.get_mut(id).unwrap().cargo.add(wanted, 1);
+		env.act(UIAction::Dock(vec![id]));
+
+		for _ in 0..600 {
+			game.advance(1. / 60.);
+		}
+
+		let env = &game.world().env;
+		let boat = env.boats.get(id).unwrap();
+		assert!(boat.path.is_none());
+		assert_eq!(boat.cargo.get(wanted), 1);
+		assert_eq!(env.treasury, Environment::STARTING_TREASURY);
+	}
+
+	#[test]
+	fn replays_carry_the_save_they_start_from() {
+		let mut game = ocean_game();
+		game.world_mut().env.act(UIAction::Place(vec2(100., 0.), 0));
+		let save = game.world().save(game.external().camera);
+
+		let options = Options {
+			load: Some(Box::new(save)),
+			..Default::default()
+		};
+		let mut game = GameState::<World>::headless((800, 600), 1, options);
+		game.record();
+		game.advance(1. / 60.);
+
+		let replay = game.stop_recording().unwrap();
+		let replayed = GameState::<World>::from_replay(&replay);
+		assert_eq!(replayed.world().env.boats.iter().count(), 1);
+		assert!(replayed.external().recording);
+	}
+
+	#[test]
+	#[should_panic]
+	fn recording_starts_with_the_world() {
+		let mut game = ocean_game();
+		game.advance(1. / 60.);
+		game.record();
+	}
+}
@@ -0,0 +1,176 @@
+//Everything needed to restore a World. Terrain isn't stored, since chunks
+//are regenerated identically from the tilemap settings, apart from the
+//tiles that have been edited since.
+
+use super::*;
+use cgmath::Vector2;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Save {
+	version: u32,
+	pub camera: Camera,
+	pub tiles: TileMapSettings,
+	pub tile_edits: Vec<(Vector2<i32>, ChunkDelta)>,
+	pub boats: Vec<Boat>,
+	pub treasury: f32,
+	pub markets: Vec<Market>,
+}
+
+//Read on its own first, so saves of other versions are rejected before
+//the rest, whose layout may have changed, is parsed
+#[derive(Deserialize)]
+struct Header {
+	version: u32,
+}
+
+impl Save {
+	//Bump whenever the format changes. Saves from other versions are rejected.
+	pub const VERSION: u32 = 6;
+	pub const QUICKSAVE: &'static str = "quicksave.ron";
+
+	pub fn new(world: &World, camera: Camera) -> Self {
+		Self {
+			version: Self::VERSION,
+			camera,
+			tiles: world.env.tiles.settings(),
+			tile_edits: world
+				.env
+				.tiles
+				.deltas()
+				.map(|(cell, delta)| (cell, delta.clone()))
+				.collect(),
+			boats: world.env.boats.iter().cloned().collect(),
+			treasury: world.env.treasury,
+			markets: world.env.markets.values().cloned().collect(),
+		}
+	}
+
+	pub fn read(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+		let text = std::fs::read_to_string(path)?;
+		let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+
+		let header: Header = ron::from_str(&text).map_err(invalid)?;
+		if header.version != Self::VERSION {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"save version {} is unsupported (expected {})",
+					header.version,
+					Self::VERSION
+				),
+			));
+		}
+
+		ron::from_str(&text).map_err(invalid)
+	}
+
+	pub fn write(&self, path: impl AsRef<std::path::Path>) -> io::Result<()> {
+		let text = ron::ser::to_string_pretty(self, Default::default())
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+		std::fs::write(path, text)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::eng::state::GameState;
+	use cgmath::*;
+
+	fn temp_path(name: &str) -> std::path::PathBuf {
+		std::env::temp_dir().join(name)
+	}
+
+	//A world with a boat sailing a path with cargo aboard, another on a
+	//trade route, a market, some money spent and an edited tile
+	fn busy_world() -> GameState<World> {
+		let mut game = GameState::<World>::headless((800, 600), 7, Default::default());
+		let env = &mut game.world_mut().env;
+
+		env.act(UIAction::Place(vec2(100., 50.), 0));
+		env.act(UIAction::Place(vec2(-300., 200.), 0));
+		let ids: Vec<_> = env.boats.iter_with_ids().map(|(id, _)| id).collect();
+
+		let mut path = Path::new(vec2(100., 50.));
+		path.add_waypoint(vec2(900., 400.));
+		let sailing = env.boats.get_mut(ids[0]).unwrap();
+		sailing.follow(path);
+		sailing.cargo.add(Good::Fish, 3);
+
+		let stop = |dock, good| Stop {
+			dock,
+			orders: vec![Order::Load(good, Some(2)), Order::Unload(good, None)],
+		};
+		let stops = vec![
+			stop(vec2(500., 0.), Good::Wood),
+			stop(vec2(-500., 0.), Good::Grain),
+		];
+		let mut itinerary = Itinerary::new(stops).unwrap();
+		itinerary.advance();
+		env.boats.get_mut(ids[1]).unwrap().itinerary = Some(itinerary);
+
+		let market = Market::generate(7, vec2(500., 0.));
+		env.markets.insert(NavGrid::tile_of(market.pos), market);
+		env.treasury = 42.5;
+		env.tiles
+			.edit_tile(vec2(64., 64.), |tile| tile.height = 0.5);
+
+		game
+	}
+
+	#[test]
+	fn saves_round_trip() {
+		let game = busy_world();
+		let saved = game.world().save(game.external().camera);
+
+		let path = temp_path("seas_save_round_trip.ron");
+		saved.write(&path).unwrap();
+
+		let mut loaded = GameState::<World>::headless((800, 600), 1, Default::default());
+		loaded.world_mut().load(Save::read(&path).unwrap());
+		let resaved = loaded.world().save(game.external().camera);
+
+		let env = &loaded.world().env;
+		assert_eq!(env.boats.iter().count(), 2);
+		assert_eq!(env.treasury, 42.5);
+		assert!(env.boats.iter().any(|boat| boat.cargo.get(Good::Fish) == 3
+			&& boat
+				.path
+				.as_ref()
+				.is_some_and(|(_, path)| path.nodes.len() == 2)));
+		assert!(env
+			.boats
+			.iter()
+			.any(|boat| boat.itinerary.as_ref().is_some_and(|it| it.next == 1)));
+
+		let ron = |save: &Save| ron::to_string(save).unwrap();
+		assert_eq!(ron(&saved), ron(&resaved));
+	}
+
+	#[test]
+	fn other_versions_are_rejected() {
+		let game = busy_world();
+		let text = ron::ser::to_string_pretty(
+			&game.world().save(game.external().camera),
+			Default::default(),
+		)
+		.unwrap();
+
+		let current = format!("version: {}", Save::VERSION);
+		assert!(text.contains(&current));
+
+		let older = text.replacen(&current, &format!("version: {}", Save::VERSION - 1), 1);
+		let path = temp_path("seas_save_old_version.ron");
+		std::fs::write(&path, older).unwrap();
+		let err = Save::read(&path).err().unwrap();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+		//Nothing past the header is parsed
+		let path = temp_path("seas_save_future_version.ron");
+		std::fs::write(&path, "(version: 999, boats: \"???\")").unwrap();
+		let err = Save::read(&path).err().unwrap();
+		assert!(err.to_string().contains("999"), "{}", err);
+	}
+}
@@ -1,10 +1,17 @@
+use super::trade::Good;
 use strum::*;
 use strum_macros::*;
 
 #[derive(EnumDiscriminants, EnumCount)]
 #[strum_discriminants(name(SignalKinds))]
 pub enum Signal {
-	BoatNearby,
+	//Sent by a boat every frame, with its current heading
+	BoatNearby { heading: (f32, f32) },
+	//Sent by a boat every frame while it is stuck on land
+	Aground,
+	//Sent to a boat for each good it trades at a dock. Amount is positive
+	//when bought, and value is what changed hands.
+	Traded { good: Good, amount: i32, value: f32 },
 	Nil,
 }
 
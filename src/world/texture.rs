@@ -1,7 +1,10 @@
 use super::*;
+use serde::{Deserialize, Serialize};
 use strum_macros::{EnumIter, IntoStaticStr};
 
-#[derive(IntoStaticStr, EnumIter, Hash, PartialEq, Debug, Eq, Clone, Copy)]
+#[derive(
+	IntoStaticStr, EnumIter, Hash, PartialEq, Debug, Eq, Clone, Copy, Serialize, Deserialize,
+)]
 pub enum Texture {
 	Flat,
 	Puffin,
@@ -12,6 +15,7 @@ pub enum Texture {
 	Raft,
 	Wave,
 	Node,
+	Wood,
 }
 
 impl TextureType for Texture {
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumCount, EnumIter};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, EnumCount, EnumIter, Serialize, Deserialize)]
+pub enum Good {
+	Wood,
+	Fish,
+	Grain,
+}
+
+impl Good {
+	//Price when a dock holds Market::TARGET_STOCK of the good
+	pub fn base_price(&self) -> f32 {
+		use Good::*;
+		match self {
+			Wood => 10.,
+			Fish => 6.,
+			Grain => 8.,
+		}
+	}
+
+	pub fn all() -> impl Iterator<Item = Self> {
+		use strum::IntoEnumIterator;
+		Self::iter()
+	}
+}
+
+//Units of each good held
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Cargo([u32; <Good as strum::EnumCount>::COUNT]);
+
+impl Cargo {
+	pub fn get(&self, good: Good) -> u32 {
+		self.0[good as usize]
+	}
+
+	pub fn add(&mut self, good: Good, amount: u32) {
+		self.0[good as usize] += amount;
+	}
+
+	//Removes up to amount of a good, returning how much was removed
+	pub fn take(&mut self, good: Good, amount: u32) -> u32 {
+		let taken = amount.min(self.get(good));
+		self.0[good as usize] -= taken;
+		taken
+	}
+
+	pub fn total(&self) -> u32 {
+		self.0.iter().sum()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.total() == 0
+	}
+}
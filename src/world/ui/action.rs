@@ -2,16 +2,20 @@ use super::*;
 use cgmath::*;
 
 pub enum UIAction {
-	Route(GridId, Path), //boat id & path
-	Place(Vector2<f32>),
+	Route(Vec<GridId>, Path, Formation), //boat ids, path & formation
+	Edit(GridId, Path, Option<usize>),   //boat id, edited path & node being dragged
+	Place(Vector2<f32>, usize),          //position & index into Environment::kinds
+	Dock(Vec<GridId>),                   //boat ids, each sent to its nearest dock
+	Trade(Vec<GridId>, Path),            //boat ids & docks to trade between
+	Build(Vector2<f32>),                 //where to build a pier
 }
 
 impl UIAction {
 	pub fn finish(mut self) -> Self {
 		use UIAction::*;
 		match &mut self {
-			Route(_, path) => path.finish(),
-			Place(_) => (),
+			Route(_, path, _) | Trade(_, path) => path.finish(),
+			Edit(..) | Place(..) | Dock(_) | Build(_) => (),
 		};
 		self
 	}
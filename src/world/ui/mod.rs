@@ -5,18 +5,141 @@ pub use action::*;
 use super::*;
 use crate::eng::*;
 use crate::window::*;
-use std::cell::RefCell;
+use cgmath::*;
+use std::cell::{Cell, RefCell};
+use winit::event::VirtualKeyCode;
 
 pub struct WorldUI {
 	action: RefCell<Option<UIAction>>,
+	selection: RefCell<Vec<GridId>>,
+	//Positions of the selected boats as of the last plan, for rendering
+	selected_pos: RefCell<Vec<Vector2<f32>>>,
+	//Control groups, bound to the number keys
+	groups: RefCell<[Vec<GridId>; 10]>,
+	//Corner of the selection box being dragged out
+	drag_start: Cell<Option<Vector2<f32>>>,
+	//Kind of boat placed on right click, cycled with tab
+	kind: Cell<usize>,
+	//That kind as of the last plan, for rendering
+	placing: RefCell<Option<BoatKind>>,
+	//Where boats have run aground, as heard in the latest update
+	aground: Vec<Vector2<f32>>,
 }
 
 impl WorldUI {
 	const SELECT_RADIUS: f32 = 50.;
+	const SELECT_COLOR: GLvec4 = GLvec4(0.3, 1., 0.4, 0.6);
+	const SELECT_SIZE: f32 = 90.;
+	const BOX_THICKNESS: f32 = 4.;
+	const AGROUND_COLOR: GLvec4 = GLvec4(1., 0.3, 0.2, 0.6);
+	const AGROUND_SIZE: f32 = 120.;
+	//Preview of the kind being placed, beside the cursor
+	const PREVIEW_OFFSET: Vector2<f32> = vec2(60., -60.);
+	const PREVIEW_ALPHA: f32 = 0.6;
+
+	const GROUP_KEYS: [VirtualKeyCode; 10] = {
+		use VirtualKeyCode::*;
+		[Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9]
+	};
 
 	pub fn new() -> Self {
 		Self {
 			action: None.into(),
+			selection: vec![].into(),
+			selected_pos: vec![].into(),
+			groups: Default::default(),
+			drag_start: None.into(),
+			kind: 0.into(),
+			placing: None.into(),
+			aground: vec![],
+		}
+	}
+
+	//Average position of the boats that still exist
+	fn centroid(world: &World, boats: &[GridId]) -> Option<Vector2<f32>> {
+		let positions: Vec<_> = boats
+			.iter()
+			.filter_map(|&id| world.env.boats.get(id))
+			.map(|boat| boat.pos)
+			.collect();
+
+		(!positions.is_empty())
+			.then(|| positions.iter().sum::<Vector2<f32>>() / positions.len() as f32)
+	}
+
+	fn select(&self, world: &World, external: &External, mouse: Vector2<f32>) {
+		use winit::event::VirtualKeyCode::*;
+
+		let mut placing = self.placing.borrow_mut();
+		if placing.is_none() {
+			*placing = world.env.kinds.get(self.kind.get()).cloned();
+		}
+
+		let mut selection = self.selection.borrow_mut();
+
+		let shift = external.key(LShift).is_down() || external.key(RShift).is_down();
+		let ctrl = external.key(LControl).is_down() || external.key(RControl).is_down();
+
+		for (key, group) in Self::GROUP_KEYS
+			.iter()
+			.zip(self.groups.borrow_mut().iter_mut())
+		{
+			if external.key(*key).pressed() {
+				if ctrl {
+					*group = selection.clone();
+				} else {
+					group.retain(|&id| world.env.boats.get(id).is_some());
+					*selection = group.clone();
+				}
+			}
+		}
+
+		if external.left_mouse.pressed() {
+			match world.env.boats.nearest(mouse, Self::SELECT_RADIUS) {
+				Some((id, _)) if shift => {
+					match selection.iter().position(|&selected| selected == id) {
+						Some(i) => {
+							selection.remove(i);
+						}
+						None => selection.push(id),
+					}
+				}
+				//Clicking a boat outside the selection selects just it.
+				//Either way, the selection is given a route.
+				Some((id, _)) => {
+					if !selection.contains(&id) {
+						*selection = vec![id];
+					}
+
+					let start = Self::centroid(world, &selection).unwrap();
+					let mut path = Path::new(start);
+					path.add_waypoint(mouse);
+
+					*self.action.borrow_mut() = Some(UIAction::Route(
+						selection.clone(),
+						path,
+						Formation::default(),
+					));
+				}
+				None => self.drag_start.set(Some(mouse)),
+			}
+		}
+
+		if external.left_mouse.released() {
+			if let Some(start) = self.drag_start.take() {
+				let lo = vec2(start.x.min(mouse.x), start.y.min(mouse.y));
+				let hi = vec2(start.x.max(mouse.x), start.y.max(mouse.y));
+
+				if !shift {
+					selection.clear();
+				}
+
+				for (id, _) in world.env.boats.query_rect(lo, hi) {
+					if !selection.contains(&id) {
+						selection.push(id);
+					}
+				}
+			}
 		}
 	}
 }
@@ -31,50 +154,148 @@ impl GameObject for WorldUI {
 
 		let mut action = self.action.borrow_mut();
 		let mouse = external.camera.screen_to_world(external.mouse_pos);
+		let idle = action.is_none();
 
 		match &mut *action {
 			Some(_) if external.key(Escape).pressed() => *action = None,
-			Some(Route(target_id, path)) => {
-				let target = world.env.boats.get(*target_id).unwrap();
+			Some(Route(boats, path, formation)) => {
+				let Some(start) = Self::centroid(world, boats) else {
+					*action = None;
+					return;
+				};
 
-				path.move_first(target.pos);
+				path.move_first(start);
 				path.move_last(mouse);
 
 				if external.left_mouse.pressed() {
 					path.add_waypoint(mouse);
 				}
+
+				if external.key(L).pressed() {
+					path.mode = path.mode.next();
+				}
+
+				if external.key(F).pressed() {
+					*formation = formation.next();
+				}
 			}
-			Some(Place(pos)) => *pos = mouse,
-			None => {
-				//If left click on raft, begin routing
-				if external.left_mouse.pressed() {
-					let target = world.env.boats.nearest(mouse, Self::SELECT_RADIUS);
-					if let Some((id, boat)) = target {
-						let mut path = Path::new(boat.pos);
-						path.add_waypoint(mouse);
+			Some(Edit(_, path, dragging)) => {
+				if external.key(L).pressed() {
+					path.mode = path.mode.next();
+				}
 
-						*action = Some(Route(id, path));
+				if external.key(Delete).pressed() || external.key(Back).pressed() {
+					if let Some(i) = path.nearest_node(mouse, Self::SELECT_RADIUS) {
+						path.remove_node(i);
+						*dragging = None;
 					}
 				}
 
-				//If right click, spawn raft
+				//Grab a node, or make one on the segment under the mouse
+				if external.left_mouse.pressed() {
+					*dragging = path.nearest_node(mouse, Self::SELECT_RADIUS).or_else(|| {
+						let (i, pos) = path.nearest_segment(mouse, Self::SELECT_RADIUS)?;
+						path.insert_node(i, pos);
+						Some(i)
+					});
+				}
+
+				if !external.left_mouse.is_down() {
+					*dragging = None;
+				}
+
+				if let Some(i) = *dragging {
+					path.move_node(i, mouse);
+				}
+			}
+			Some(Place(pos, _)) => *pos = mouse,
+			Some(Trade(_, path)) => {
+				path.move_last(mouse);
+
+				if external.left_mouse.pressed() {
+					path.add_waypoint(mouse);
+				}
+			}
+			Some(Dock(_)) | Some(Build(_)) => (),
+			None => {
+				if external.key(Tab).pressed() {
+					let kinds = &world.env.kinds;
+					let kind = (self.kind.get() + 1) % kinds.len();
+					self.kind.set(kind);
+					*self.placing.borrow_mut() = None;
+				}
+
+				//Send the selection to moor at the nearest docks
+				if external.key(H).pressed() && !self.selection.borrow().is_empty() {
+					*action = Some(Dock(self.selection.borrow().clone()));
+				}
+
+				//Click docks for the selection to trade between
+				if external.key(T).pressed() && !self.selection.borrow().is_empty() {
+					let mut stops = Path::new(mouse);
+					stops.mode = PathMode::Loop;
+					*action = Some(Trade(self.selection.borrow().clone(), stops));
+				}
+
+				//Build piers under the mouse
+				if external.key(B).pressed() {
+					*action = Some(Build(mouse));
+				}
+
+				//If right click on a boat with a path, edit it. Otherwise,
+				//spawn a boat of the chosen kind.
 				if external.right_mouse.pressed() {
-					*action = Some(Place(mouse));
+					let target = world.env.boats.nearest(mouse, Self::SELECT_RADIUS);
+					*action = match target {
+						Some((
+							id,
+							Boat {
+								path: Some((_, path)),
+								..
+							},
+						)) => Some(Edit(id, path.clone(), None)),
+						_ => Some(Place(mouse, self.kind.get())),
+					};
 				}
 			}
 		}
+		std::mem::drop(action);
+
+		//Left click or drag to select boats, and begin routing them when
+		//clicking one
+		if idle && self.action.borrow().is_none() {
+			self.select(world, external, mouse);
+		}
+
+		let mut selection = self.selection.borrow_mut();
+		selection.retain(|&id| world.env.boats.get(id).is_some());
+
+		*self.selected_pos.borrow_mut() = selection
+			.iter()
+			.filter_map(|&id| world.env.boats.get(id))
+			.map(|boat| boat.pos)
+			.collect();
 	}
 
 	fn update(
 		&mut self,
 		external: &External,
-		_messenger: &Messenger<Signal>,
+		messenger: &Messenger<Signal>,
 	) -> Option<Self::Action> {
 		use winit::event::VirtualKeyCode::*;
 		use UIAction::*;
+		type SignalTy = <Signal as SignalType>::SignalKinds;
+
+		let camera = external.camera.pos;
+		self.aground = messenger
+			.local_receive(camera, f32::INFINITY, &[SignalTy::Aground])
+			.filter_map(|dispatch| dispatch.pos())
+			.map(Vector2::from)
+			.filter(|&pos| external.point_in_view(pos))
+			.collect();
 
 		match self.action.get_mut() {
-			Some(Place(_)) => self.action.take(),
+			Some(Place(..)) | Some(Dock(_)) | Some(Build(_)) => self.action.take(),
 			_ => {
 				if external.key(Space).pressed() {
 					self.action.take().map(|action| action.finish())
@@ -89,8 +310,67 @@ impl GameObject for WorldUI {
 		use UIAction::*;
 
 		match &*self.action.borrow() {
-			Some(Route(_, path)) => path.render(win),
+			Some(Route(_, path, _)) | Some(Edit(_, path, _)) | Some(Trade(_, path)) => {
+				path.render(win)
+			}
 			_ => (),
 		}
+
+		let marker = Instance {
+			color_tint: Self::SELECT_COLOR,
+			scale: GLvec2(Self::SELECT_SIZE, Self::SELECT_SIZE),
+			..win.external().instance(Texture::Node)
+		};
+
+		for &pos in self.selected_pos.borrow().iter() {
+			win.queue(Instance {
+				position: pos.into(),
+				..marker
+			});
+		}
+
+		for &pos in &self.aground {
+			win.queue(Instance {
+				position: pos.into(),
+				color_tint: Self::AGROUND_COLOR,
+				scale: GLvec2(Self::AGROUND_SIZE, Self::AGROUND_SIZE),
+				..marker
+			});
+		}
+
+		if let Some(kind) = &*self.placing.borrow() {
+			win.set_title(&format!("{} - placing {}", World::TITLE, kind.name));
+
+			let external = win.external();
+			let mouse = external.camera.screen_to_world(external.mouse_pos);
+			//Camera scale is half the height of the view
+			let offset =
+				Self::PREVIEW_OFFSET * 2. * external.camera.scale / external.win_size.1 as f32;
+			let preview = kind.instance(win);
+			let GLvec4(r, g, b, a) = preview.color_tint;
+			win.queue(Instance {
+				position: (mouse + offset).into(),
+				color_tint: GLvec4(r, g, b, a * Self::PREVIEW_ALPHA),
+				..preview
+			});
+		}
+
+		if let Some(start) = self.drag_start.get() {
+			let external = win.external();
+			let end = external.camera.screen_to_world(external.mouse_pos);
+
+			let corners = [start, vec2(end.x, start.y), end, vec2(start.x, end.y)];
+			for i in 0..4 {
+				let line = win.external().line_instance(
+					corners[i],
+					corners[(i + 1) % 4],
+					Self::BOX_THICKNESS,
+				);
+				win.queue(Instance {
+					color_tint: Self::SELECT_COLOR,
+					..line
+				});
+			}
+		}
 	}
 }